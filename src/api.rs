@@ -1,9 +1,41 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use std::fmt::Display;
+
 use async_trait::async_trait;
-use reqwest::header::HeaderMap;
-use reqwest::{IntoUrl, Method, RequestBuilder};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, IntoUrl, Method, RequestBuilder, Response, StatusCode, Url};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 
+use crate::error::ConfigError;
+
+static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Number of times a rate limited request is retried before the error is returned
+const MAX_RATE_LIMIT_RETRIES: u32 = 5;
+/// Longest wait for the rate limit reset, longer ones (e.g. GitHub's hourly limit) fail right away
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(120);
+
+/// Configures the HTTP client shared by all the APIs.
+///
+/// Has to be called before any request is made, otherwise the client is created with default settings.
+pub fn configure_http_client(timeout: Duration) -> anyhow::Result<()> {
+    let client = Client::builder()
+        .timeout(timeout)
+        .build()
+        .map_err(|e| ConfigError(format!("Cannot configure HTTP client: {}", e)))?;
+    HTTP_CLIENT
+        .set(client)
+        .map_err(|_| anyhow::anyhow!("HTTP client is already configured"))
+}
+
+/// HTTP client shared by all the APIs, so the connections are pooled between requests
+pub(crate) fn http_client() -> &'static Client {
+    HTTP_CLIENT.get_or_init(Client::new)
+}
+
 pub(crate) struct BasicAuth<'a>(&'a str, &'a str);
 impl<'a> BasicAuth<'a> {
     pub fn new(username: &'a str, password: &'a str) -> Self {
@@ -11,12 +43,128 @@ impl<'a> BasicAuth<'a> {
     }
 }
 
+#[derive(Debug)]
+pub(crate) enum ApiError {
+    NotFound,
+    Unauthorized,
+    RateLimited { retry_after: Option<Duration> },
+    Unexpected { status: StatusCode, body: String },
+    Decode { url: Url, source: serde_json::Error },
+    Transport(reqwest::Error),
+}
+
+impl ApiError {
+    pub async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        match status {
+            StatusCode::NOT_FOUND => ApiError::NotFound,
+            StatusCode::UNAUTHORIZED => ApiError::Unauthorized,
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited {
+                retry_after: retry_after(response.headers()),
+            },
+            StatusCode::FORBIDDEN if is_rate_limit_exceeded(response.headers()) => {
+                ApiError::RateLimited {
+                    retry_after: retry_after(response.headers()),
+                }
+            }
+            _ => ApiError::Unexpected {
+                status,
+                body: response.text().await.unwrap_or_default(),
+            },
+        }
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::NotFound => write!(f, "resource not found"),
+            ApiError::Unauthorized => write!(f, "unauthorized, check the credentials"),
+            ApiError::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(
+                f,
+                "rate limit exceeded, retry after {} seconds",
+                retry_after.as_secs()
+            ),
+            ApiError::RateLimited { retry_after: None } => write!(f, "rate limit exceeded"),
+            ApiError::Unexpected { status, body } => {
+                write!(
+                    f,
+                    "unexpected response ({}): {}",
+                    status,
+                    body_snippet(body)
+                )
+            }
+            ApiError::Decode { url, source } => {
+                write!(f, "cannot decode response from {}: {}", url, source)
+            }
+            ApiError::Transport(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Transport(err) => Some(err),
+            ApiError::Decode { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        ApiError::Transport(err)
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Time to wait before retrying the rate limited request, `None` when it shouldn't be retried.
+///
+/// Waits for `Retry-After` when the API sends it, otherwise backs off exponentially from 1 second.
+fn rate_limit_wait(error: &ApiError, attempt: u32) -> Option<Duration> {
+    let retry_after = match error {
+        ApiError::RateLimited { retry_after } if attempt < MAX_RATE_LIMIT_RETRIES => retry_after,
+        _ => return None,
+    };
+    let wait = retry_after.unwrap_or_else(|| Duration::from_secs(1 << attempt));
+    (wait <= MAX_RATE_LIMIT_WAIT).then_some(wait)
+}
+
+fn is_rate_limit_exceeded(headers: &HeaderMap) -> bool {
+    let remaining = headers
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok());
+
+    headers.contains_key(RETRY_AFTER) || remaining == Some("0")
+}
+
+fn body_snippet(body: &str) -> String {
+    const MAX_SNIPPET_LENGTH: usize = 200;
+
+    if body.chars().count() > MAX_SNIPPET_LENGTH {
+        let snippet = body.chars().take(MAX_SNIPPET_LENGTH).collect::<String>();
+        format!("{}...", snippet)
+    } else {
+        body.to_string()
+    }
+}
+
 #[async_trait]
 pub(crate) trait ApiClient {
-    fn basic_auth(&self) -> Option<BasicAuth>;
+    fn basic_auth(&self) -> Option<BasicAuth<'_>>;
     fn headers(&self) -> Option<HeaderMap>;
 
-    async fn get<T, U>(&self, url: U) -> reqwest::Result<T>
+    async fn get<T, U>(&self, url: U) -> Result<T, ApiError>
     where
         T: DeserializeOwned,
         U: IntoUrl + Send,
@@ -25,7 +173,7 @@ pub(crate) trait ApiClient {
             .await
     }
 
-    async fn post<T, U, B>(&self, url: U, body: Option<B>) -> reqwest::Result<T>
+    async fn post<T, U, B>(&self, url: U, body: Option<B>) -> Result<T, ApiError>
     where
         T: DeserializeOwned,
         U: IntoUrl + Send,
@@ -34,7 +182,7 @@ pub(crate) trait ApiClient {
         self.request(Method::POST, url, body).await
     }
 
-    async fn put<T, U, B>(&self, url: U, body: Option<B>) -> reqwest::Result<T>
+    async fn put<T, U, B>(&self, url: U, body: Option<B>) -> Result<T, ApiError>
     where
         T: DeserializeOwned,
         U: IntoUrl + Send,
@@ -43,7 +191,7 @@ pub(crate) trait ApiClient {
         self.request(Method::PUT, url, body).await
     }
 
-    async fn patch<T, U, B>(&self, url: U, body: Option<B>) -> reqwest::Result<T>
+    async fn patch<T, U, B>(&self, url: U, body: Option<B>) -> Result<T, ApiError>
     where
         T: DeserializeOwned,
         U: IntoUrl + Send,
@@ -52,28 +200,53 @@ pub(crate) trait ApiClient {
         self.request(Method::PATCH, url, body).await
     }
 
-    async fn request<T, U, B>(&self, method: Method, url: U, body: Option<B>) -> reqwest::Result<T>
+    #[tracing::instrument(
+        level = "debug",
+        name = "api_request",
+        skip_all,
+        fields(%method, url = tracing::field::Empty, status = tracing::field::Empty)
+    )]
+    async fn request<T, U, B>(&self, method: Method, url: U, body: Option<B>) -> Result<T, ApiError>
     where
         T: DeserializeOwned,
         U: IntoUrl + Send,
         B: Serialize + Send,
     {
-        let client = reqwest::Client::new().request(method, url);
-        let mut builder = self.build_common_parts(client);
-        if let Some(body) = body {
-            builder = builder.json(&body);
-        }
+        let url = url.into_url()?;
+        let span = tracing::Span::current();
+        span.record("url", &url.as_str());
+
+        let mut attempt = 0;
+        let response = loop {
+            let client = http_client().request(method.clone(), url.clone());
+            let mut builder = self.build_common_parts(client);
+            if let Some(body) = &body {
+                builder = builder.json(body);
+            }
 
-        let response = builder.send().await?.error_for_status()?;
+            let response = builder.send().await?;
+            if response.status().is_success() {
+                break response;
+            }
+
+            span.record("status", &response.status().as_u16());
+            let error = ApiError::from_response(response).await;
+            match rate_limit_wait(&error, attempt) {
+                Some(wait) => {
+                    attempt += 1;
+                    tokio::time::sleep(wait).await;
+                }
+                None => return Err(error),
+            }
+        };
+        span.record("status", &response.status().as_u16());
 
         let mut body = response.text().await?;
         if body.is_empty() {
             body = "{}".to_string();
         }
 
-        let response = serde_json::from_str(&body).unwrap();
-
-        Ok(response)
+        serde_json::from_str(&body).map_err(|source| ApiError::Decode { url, source })
     }
 
     #[inline]
@@ -1,7 +1,9 @@
-use reqwest::header::HeaderMap;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::Url;
 use std::fmt::{Display, Formatter};
 
-use crate::api::{ApiClient, BasicAuth};
+use crate::api::{ApiClient, ApiError, BasicAuth};
+use crate::cache;
 use crate::config::BitbucketConfig;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
@@ -32,11 +34,53 @@ pub struct Repository {
     pub name: String,
     #[serde(rename = "mainbranch")]
     pub main_branch: Branch,
+    #[serde(default)]
+    pub project: Option<Project>,
+    /// ISO 8601 timestamp of the last update
+    #[serde(default)]
+    pub updated_on: Option<String>,
+    /// Size of the repository in bytes
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Empty when not set
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub website: Option<String>,
 }
 
 impl Display for Repository {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} (branch: {})", self.name, self.main_branch)
+        write!(f, "{} (branch: {}", self.name, self.main_branch)?;
+        if let Some(updated_on) = &self.updated_on {
+            // Only the date part of the timestamp is relevant here
+            write!(
+                f,
+                ", updated: {}",
+                updated_on.get(..10).unwrap_or(updated_on)
+            )?;
+        }
+        if let Some(size) = self.size {
+            write!(f, ", size: {}", format_size(size))?;
+        }
+        write!(f, ")")
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
     }
 }
 
@@ -81,6 +125,28 @@ impl Display for Branch {
     }
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct User {
+    pub nickname: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Commit {
+    author: CommitAuthor,
+}
+
+#[derive(Deserialize, Debug)]
+struct CommitAuthor {
+    user: Option<User>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DeployKey {
+    /// Public part of the key
+    pub key: String,
+    pub label: String,
+}
+
 pub(crate) struct BitbucketApi {
     config: BitbucketConfig,
 }
@@ -92,13 +158,19 @@ impl BitbucketApi {
         }
     }
 
+    pub fn workspace_name(&self) -> &str {
+        &self.config.workspace_name
+    }
+
     pub async fn get_projects(&self) -> Result<Vec<Project>, anyhow::Error> {
         let url = format!(
-            "https://api.bitbucket.org/2.0/workspaces/{workspace}/projects",
+            "{api_url}/2.0/workspaces/{workspace}/projects",
+            api_url = &self.config.api_url,
             workspace = &self.config.workspace_name
         );
 
-        let projects = self.get_all_pages(url).await?;
+        let cache_key = format!("bitbucket-{}-projects", &self.config.workspace_name);
+        let projects = cache::cached(&cache_key, self.get_all_pages(url)).await?;
 
         Ok(projects)
     }
@@ -107,47 +179,96 @@ impl BitbucketApi {
         &self,
         project_key: &str,
     ) -> Result<Vec<Repository>, anyhow::Error> {
-        let url = format!("https://api.bitbucket.org/2.0/repositories/{workspace}?q=project.key=\"{key}\"&pagelen={pagelen}", workspace = &self.config.workspace_name, key = project_key, pagelen = 100);
-        let res: PageResponse<Repository> = self.get(url).await?;
+        let mut url = Url::parse(&format!(
+            "{api_url}/2.0/repositories/{workspace}",
+            api_url = &self.config.api_url,
+            workspace = &self.config.workspace_name,
+        ))?;
+        url.query_pairs_mut()
+            .append_pair("q", &format!("project.key={}", bbql_string(project_key)))
+            .append_pair("pagelen", "100");
+        let url = url.to_string();
 
-        Ok(res.values)
+        let cache_key = format!(
+            "bitbucket-{}-{}-repositories",
+            &self.config.workspace_name, project_key
+        );
+        let repositories = cache::cached(&cache_key, self.get_all_pages(url)).await?;
+
+        Ok(repositories)
     }
 
     pub async fn get_repository_branches(
         &self,
         full_repo_name: &str,
     ) -> anyhow::Result<Vec<Branch>> {
-        let url = format!("https://api.bitbucket.org/2.0/repositories/{full_repo_name}/refs/branches?pagelen={pagelen}", full_repo_name = full_repo_name, pagelen = 100);
+        let url = format!(
+            "{api_url}/2.0/repositories/{full_repo_name}/refs/branches?pagelen={pagelen}",
+            api_url = &self.config.api_url,
+            full_repo_name = full_repo_name,
+            pagelen = 100
+        );
 
         let branches = self.get_all_pages(url).await?;
 
         Ok(branches)
     }
 
-    #[cfg(feature = "circleci")]
+    /// Returns Bitbucket users who authored any of the latest commits in the repository.
+    ///
+    /// Commits made with an email not linked to any Bitbucket account are skipped.
+    pub async fn get_repository_committers(
+        &self,
+        full_repo_name: &str,
+    ) -> anyhow::Result<Vec<User>> {
+        let url = format!(
+            "{api_url}/2.0/repositories/{full_repo_name}/commits?pagelen={pagelen}",
+            api_url = &self.config.api_url,
+            full_repo_name = full_repo_name,
+            pagelen = 100
+        );
+
+        let response: PageResponse<Commit> = self.get(url).await?;
+        let mut committers: Vec<User> = vec![];
+        for user in response.values.into_iter().filter_map(|c| c.author.user) {
+            if !committers.iter().any(|c| c.nickname == user.nickname) {
+                committers.push(user);
+            }
+        }
+
+        Ok(committers)
+    }
+
+    pub async fn get_deploy_keys(&self, full_repo_name: &str) -> anyhow::Result<Vec<DeployKey>> {
+        let url = format!(
+            "{api_url}/2.0/repositories/{full_repo_name}/deploy-keys?pagelen={pagelen}",
+            api_url = &self.config.api_url,
+            full_repo_name = full_repo_name,
+            pagelen = 100
+        );
+
+        let keys = self.get_all_pages(url).await?;
+
+        Ok(keys)
+    }
+
     pub async fn get_repository(&self, repo_name: &str) -> anyhow::Result<Option<Repository>> {
         let url = format!(
-            "https://api.bitbucket.org/2.0/repositories/{repo_name}",
+            "{api_url}/2.0/repositories/{repo_name}",
+            api_url = &self.config.api_url,
             repo_name = repo_name
         );
         let res = self.get(url).await;
 
         match res {
             Ok(res) => Ok(Some(res)),
-            Err(err) => match err.status() {
-                Some(status) => {
-                    if status.as_u16() == 404 {
-                        Ok(None)
-                    } else {
-                        Err(anyhow::anyhow!(
-                            "Error: Repository {} was not found in Bitbucket account: {}",
-                            &repo_name,
-                            err
-                        ))
-                    }
-                }
-                None => Err(anyhow::anyhow!("Unknown error: {}", err)),
-            },
+            Err(ApiError::NotFound) => Ok(None),
+            Err(err @ ApiError::Transport(_)) => Err(anyhow::anyhow!("Unknown error: {}", err)),
+            Err(err) => Err(anyhow::anyhow!(
+                "Error: Repository {} was not found in Bitbucket account: {}",
+                &repo_name,
+                err
+            )),
         }
     }
 
@@ -172,12 +293,177 @@ impl BitbucketApi {
     }
 }
 
+/// Quotes the value as a string literal of BBQL (Bitbucket query language)
+fn bbql_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
 impl ApiClient for BitbucketApi {
-    fn basic_auth(&self) -> Option<BasicAuth> {
-        Some(BasicAuth::new(&self.config.username, &self.config.password))
+    fn basic_auth(&self) -> Option<BasicAuth<'_>> {
+        match self.config.access_token {
+            // The access token is sent in the headers instead
+            Some(_) => None,
+            None => Some(BasicAuth::new(&self.config.username, &self.config.password)),
+        }
     }
 
     fn headers(&self) -> Option<HeaderMap> {
-        None
+        let access_token = self.config.access_token.as_ref()?;
+        let mut value = HeaderValue::from_str(&format!("Bearer {}", access_token))
+            .expect("access token is checked by Config::validate");
+        value.set_sensitive(true);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, value);
+        Some(headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn api(server: &MockServer) -> BitbucketApi {
+        BitbucketApi::new(&BitbucketConfig {
+            username: "user".to_string(),
+            password: "password".to_string(),
+            access_token: None,
+            workspace_name: "workspace".to_string(),
+            api_url: server.uri(),
+        })
+    }
+
+    fn project(key: &str) -> serde_json::Value {
+        json!({ "uuid": format!("{{{}}}", key), "key": key, "name": key.to_lowercase() })
+    }
+
+    #[tokio::test]
+    async fn get_projects_follows_next_links() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/workspaces/workspace/projects"))
+            .and(query_param_is_missing("page"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "values": [project("FIRST"), project("SECOND")],
+                "next": format!("{}/2.0/workspaces/workspace/projects?page=2", server.uri()),
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/workspaces/workspace/projects"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "values": [project("THIRD")],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let projects = api(&server).get_projects().await.unwrap();
+
+        let keys = projects.iter().map(|p| p.get_key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["FIRST", "SECOND", "THIRD"]);
+    }
+
+    #[tokio::test]
+    async fn get_projects_retries_rate_limited_page() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/workspaces/workspace/projects"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/workspaces/workspace/projects"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "values": [project("FIRST")],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let projects = api(&server).get_projects().await.unwrap();
+
+        let keys = projects.iter().map(|p| p.get_key()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["FIRST"]);
+    }
+
+    #[tokio::test]
+    async fn get_repository_returns_none_when_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/repositories/workspace/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let repository = api(&server)
+            .get_repository("workspace/missing")
+            .await
+            .unwrap();
+
+        assert!(repository.is_none());
+    }
+
+    #[tokio::test]
+    async fn get_project_repositories_filters_by_key_with_special_characters() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/repositories/workspace"))
+            .and(query_param("q", "project.key=\"NEW PROJECT-1\""))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "values": [{
+                    "links": { "clone": [] },
+                    "full_name": "workspace/repo",
+                    "name": "repo",
+                    "mainbranch": { "name": "master" },
+                }],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let repositories = api(&server)
+            .get_project_repositories("NEW PROJECT-1")
+            .await
+            .unwrap();
+
+        let names = repositories
+            .iter()
+            .map(|r| &r.full_name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["workspace/repo"]);
+    }
+
+    #[tokio::test]
+    async fn sends_access_token_as_bearer_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/2.0/workspaces/workspace/projects"))
+            .and(header("Authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "values": [project("FIRST")],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let mut api = api(&server);
+        api.config.access_token = Some("token".to_string());
+
+        let projects = api.get_projects().await.unwrap();
+
+        assert_eq!(projects.len(), 1);
+    }
+
+    #[test]
+    fn bbql_string_escapes_quotes() {
+        assert_eq!(bbql_string(r#"A "B" \C"#), r#""A \"B\" \\C""#);
     }
 }
@@ -0,0 +1,93 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ConfigError;
+
+static CACHE: OnceLock<Cache> = OnceLock::new();
+
+struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+    refresh: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    /// Seconds since UNIX epoch
+    fetched_at: u64,
+    data: T,
+}
+
+/// Enables on-disk cache of the projects, teams and repositories lists fetched from the APIs.
+///
+/// Cached lists older than `ttl` are fetched again, `refresh` ignores all the cached lists.
+pub fn configure_cache(ttl: Duration, refresh: bool) -> anyhow::Result<()> {
+    let dir = cache_dir()?;
+    CACHE
+        .set(Cache { dir, ttl, refresh })
+        .map_err(|_| anyhow::anyhow!("Cache is already configured"))
+}
+
+fn cache_dir() -> Result<PathBuf, ConfigError> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .ok_or_else(|| ConfigError("Cannot determine cache directory, set $HOME".to_string()))?;
+    Ok(base.join("migrate-bb-to-gh"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Returns the data cached under the `key`, calling `fetch` (and caching its result)
+/// when the cache is disabled, expired or cannot be read.
+pub(crate) async fn cached<T, E, F>(key: &str, fetch: F) -> anyhow::Result<T>
+where
+    T: Serialize + DeserializeOwned,
+    F: Future<Output = Result<T, E>>,
+    anyhow::Error: From<E>,
+{
+    let cache = match CACHE.get() {
+        Some(cache) => cache,
+        None => return Ok(fetch.await?),
+    };
+
+    let file_name = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    let path = cache.dir.join(format!("{}.json", file_name));
+
+    if !cache.refresh {
+        let entry = std::fs::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<Entry<T>>(&contents).ok());
+        if let Some(entry) = entry {
+            if now().saturating_sub(entry.fetched_at) < cache.ttl.as_secs() {
+                return Ok(entry.data);
+            }
+        }
+    }
+
+    let entry = Entry {
+        fetched_at: now(),
+        data: fetch.await?,
+    };
+    // Failing to write the cache shouldn't break the command, the data is fetched next time
+    if std::fs::create_dir_all(&cache.dir).is_ok() {
+        if let Ok(contents) = serde_json::to_vec(&entry) {
+            let _ = std::fs::write(&path, contents);
+        }
+    }
+
+    Ok(entry.data)
+}
@@ -0,0 +1,72 @@
+use crate::bitbucket::BitbucketApi;
+#[cfg(feature = "circleci")]
+use crate::circleci::api::{CircleCiApi, VCSProvider};
+use crate::config::Config;
+use crate::git;
+use crate::github::GithubApi;
+use crate::spinner;
+
+struct CheckResult {
+    name: &'static str,
+    result: anyhow::Result<()>,
+}
+
+/// Verifies credentials and connectivity of all the services used during the migration.
+///
+/// Returns `true` when all the checks passed.
+pub async fn run(config: &Config) -> anyhow::Result<bool> {
+    let spinner = spinner::create_spinner("Checking credentials and connectivity...");
+
+    let bitbucket = BitbucketApi::new(&config.bitbucket);
+    let github = GithubApi::new(&config.github);
+
+    let mut results = vec![
+        CheckResult {
+            name: "Bitbucket API",
+            result: bitbucket.get_projects().await.map(|_| ()),
+        },
+        CheckResult {
+            name: "GitHub API",
+            result: if github.is_user_account() {
+                github.verify_token_scopes().await
+            } else {
+                github.get_teams().await.map(|_| ())
+            },
+        },
+    ];
+
+    #[cfg(feature = "circleci")]
+    {
+        let circleci = CircleCiApi::new(&config.circleci);
+        results.push(CheckResult {
+            name: "CircleCI API",
+            result: circleci.get_contexts(VCSProvider::GitHub).await.map(|_| ()),
+        });
+    }
+
+    let keys_dir = git::KeysDir::new()?;
+    let passphrase = config.git.ssh_key_passphrase.as_deref();
+    let pull_key_path = keys_dir.store_ssh_key("pull", &config.git.pull_ssh_key, passphrase)?;
+    let push_key_path = keys_dir.store_ssh_key("push", &config.git.push_ssh_key, passphrase)?;
+    results.push(CheckResult {
+        name: "Bitbucket SSH (pull key)",
+        result: git::check_ssh_access("bitbucket.org", &pull_key_path),
+    });
+    results.push(CheckResult {
+        name: "GitHub SSH (push key)",
+        result: git::check_ssh_access("github.com", &push_key_path),
+    });
+    drop(keys_dir);
+
+    spinner.finish_and_clear();
+
+    let name_width = results.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    for check in &results {
+        match &check.result {
+            Ok(_) => println!("{:<width$}  OK", check.name, width = name_width),
+            Err(e) => println!("{:<width$}  FAIL ({})", check.name, e, width = name_width),
+        }
+    }
+
+    Ok(results.iter().all(|r| r.result.is_ok()))
+}
@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use crate::migration_file::{self, ActionEntry};
+
+const SECRET_MASK: &str = "****";
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum Action {
@@ -16,6 +20,14 @@ pub enum Action {
         repository_name: String,
         branch: String,
     },
+    UpdateContextVariables {
+        context_name: String,
+        variables: Vec<EnvVar>,
+    },
+    SetProjectEnvironmentalVariables {
+        repository_name: String,
+        variables: Vec<EnvVar>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -24,8 +36,22 @@ pub struct EnvVar {
     pub value: String,
 }
 
+impl EnvVar {
+    /// Value of the variable to show to the user, masked unless `show_secrets` is set
+    pub fn display_value(&self, show_secrets: bool) -> &str {
+        if show_secrets {
+            &self.value
+        } else {
+            SECRET_MASK
+        }
+    }
+}
+
 impl Action {
-    pub fn describe(&self) -> String {
+    /// Describes the action in a human-readable form.
+    ///
+    /// Values of the variables are masked unless `show_secrets` is set.
+    pub fn describe(&self, show_secrets: bool) -> String {
         match self {
             Action::MoveEnvironmentalVariables {
                 from_repository_name,
@@ -43,7 +69,27 @@ impl Action {
                 variables.len(),
                 variables
                     .iter()
-                    .map(|e| format!("  {}={}", e.name, e.value))
+                    .map(|e| format!("  {}={}", e.name, e.display_value(show_secrets)))
+                    .collect::<Vec<_>>()
+                    .join(",\n"),
+            ),
+            Action::UpdateContextVariables { context_name, variables } => format!(
+                "Update {} variables of existing '{}' context:\n{}",
+                variables.len(),
+                context_name,
+                variables
+                    .iter()
+                    .map(|e| format!("  {}={}", e.name, e.display_value(show_secrets)))
+                    .collect::<Vec<_>>()
+                    .join(",\n"),
+            ),
+            Action::SetProjectEnvironmentalVariables { repository_name, variables } => format!(
+                "Set {} environmental variables in '{}' project on GitHub:\n{}",
+                variables.len(),
+                repository_name,
+                variables
+                    .iter()
+                    .map(|e| format!("  {}={}", e.name, e.display_value(show_secrets)))
                     .collect::<Vec<_>>()
                     .join(",\n"),
             ),
@@ -56,16 +102,19 @@ impl Action {
     }
 }
 
-pub fn describe_actions(actions: &[Action]) -> String {
-    let actions_list = actions
-        .iter()
-        .enumerate()
-        .map(|(idx, action)| format!("{}. {}", idx + 1, action.describe()))
-        .collect::<Vec<_>>()
-        .join("\n");
-    format!(
-        "There are {} actions to be done during migration:\n{}",
-        actions.len(),
-        actions_list
+pub fn describe_actions(actions: &[Action], show_secrets: bool) -> String {
+    migration_file::describe(actions.iter().map(|action| (action, true)), |action| {
+        action.describe(show_secrets)
+    })
+}
+
+/// Describes actions of the migration file, marking the disabled ones as skipped
+pub(crate) fn describe_action_entries(
+    entries: &[ActionEntry<Action>],
+    show_secrets: bool,
+) -> String {
+    migration_file::describe(
+        entries.iter().map(|entry| (&entry.action, entry.enabled)),
+        |action| action.describe(show_secrets),
     )
 }
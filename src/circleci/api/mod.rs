@@ -10,7 +10,7 @@ use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Url;
 use serde::de::DeserializeOwned;
 
-use crate::api::{ApiClient, BasicAuth};
+use crate::api::{ApiClient, ApiError, BasicAuth};
 pub(crate) use models::{Context, ContextVariable, EnvVar};
 
 const AUTH_HEADER: &str = "circle-token";
@@ -34,7 +34,7 @@ pub(crate) struct CircleCiApi {
 }
 
 impl ApiClient for CircleCiApi {
-    fn basic_auth(&self) -> Option<BasicAuth> {
+    fn basic_auth(&self) -> Option<BasicAuth<'_>> {
         None
     }
 
@@ -57,38 +57,69 @@ impl CircleCiApi {
         }
     }
 
+    fn api_v2_url(&self, path: &str) -> String {
+        format!(
+            "{}/api/v2/{}",
+            self.config.base_url.trim_end_matches('/'),
+            path
+        )
+    }
+
+    fn api_v1_url(&self, path: &str) -> String {
+        format!(
+            "{}/api/v1.1/{}",
+            self.config.base_url.trim_end_matches('/'),
+            path
+        )
+    }
+
     pub async fn get_env_vars(
         &self,
         vcs: VCSProvider,
         full_repo_name: &str,
     ) -> anyhow::Result<Vec<EnvVar>> {
         let project_slug = format!("{}/{}", vcs.slug_prefix(), full_repo_name);
-        let url = format!(
-            "https://circleci.com/api/v2/project/{project_slug}/envvar",
+        let url = self.api_v2_url(&format!(
+            "project/{project_slug}/envvar",
             project_slug = project_slug,
-        );
+        ));
 
-        let res: Result<PageResponse<EnvVar>, reqwest::Error> = self.get(url).await;
+        let res: Result<PageResponse<EnvVar>, ApiError> = self.get(url).await;
         let items = match res {
             Ok(res) => res.items,
-            Err(err) => {
-                if let Some(code) = err.status() {
-                    if code == 404 {
-                        return Ok(vec![]);
-                    }
-                }
-                return Err(anyhow::anyhow!("Failed to get env vars: {}", err));
-            }
+            Err(ApiError::NotFound) => return Ok(vec![]),
+            Err(err) => return Err(anyhow::anyhow!("Failed to get env vars: {}", err)),
         };
 
         Ok(items)
     }
 
+    pub async fn create_project_env_var(
+        &self,
+        vcs: VCSProvider,
+        full_repo_name: &str,
+        name: &str,
+        value: &str,
+    ) -> anyhow::Result<EnvVar> {
+        let project_slug = format!("{}/{}", vcs.slug_prefix(), full_repo_name);
+        let url = self.api_v2_url(&format!(
+            "project/{project_slug}/envvar",
+            project_slug = project_slug,
+        ));
+        let body = EnvVar {
+            name: name.to_string(),
+            value: value.to_string(),
+        };
+
+        let var = self.post(url, Some(body)).await?;
+        Ok(var)
+    }
+
     pub async fn get_contexts(&self, vcs: VCSProvider) -> anyhow::Result<Vec<Context>> {
-        let url = format!(
-            "https://circleci.com/api/v2/context?owner-id={org_id}",
+        let url = self.api_v2_url(&format!(
+            "context?owner-id={org_id}",
             org_id = self.org_id(vcs)
-        );
+        ));
 
         let contexts = self.get_all_pages(&url).await?;
 
@@ -99,10 +130,10 @@ impl CircleCiApi {
         &self,
         context_id: &str,
     ) -> anyhow::Result<Vec<ContextVariable>> {
-        let url = format!(
-            "https://circleci.com/api/v2/context/{context_id}/environment-variable",
+        let url = self.api_v2_url(&format!(
+            "context/{context_id}/environment-variable",
             context_id = context_id
-        );
+        ));
 
         let res: PageResponse<ContextVariable> = self.get(url).await?;
 
@@ -115,10 +146,10 @@ impl CircleCiApi {
         to_repo_name: &str,
         env_vars: &[String],
     ) -> Result<(), anyhow::Error> {
-        let url = format!(
-            "https://circleci.com/api/v1.1/project/bitbucket/{repo_name}/info/export-environment",
+        let url = self.api_v1_url(&format!(
+            "project/bitbucket/{repo_name}/info/export-environment",
             repo_name = from_repo_name
-        );
+        ));
         let body = ExportEnvironmentBody {
             projects: vec![format!("https://github.com/{}", to_repo_name)],
             env_vars: env_vars.to_vec(),
@@ -129,35 +160,55 @@ impl CircleCiApi {
         //
         // Usually, 2 requests suffice, but `MAX_ATTEMPTS` is set to a greater value just in case.
 
-        let mut variables = vec![];
+        let mut missing = env_vars.to_vec();
         let mut attempts_made = 0;
 
         const MAX_ATTEMPTS: u8 = 5;
 
-        while variables.len() < env_vars.len() && attempts_made < MAX_ATTEMPTS {
+        while !missing.is_empty() && attempts_made < MAX_ATTEMPTS {
             let _: serde_json::Value = self.post(&url, Some(&body)).await?;
-            variables = self.get_env_vars(VCSProvider::GitHub, to_repo_name).await?;
+            let variables = self.get_env_vars(VCSProvider::GitHub, to_repo_name).await?;
+            missing.retain(|name| !variables.iter().any(|v| &v.name == name));
             attempts_made += 1;
         }
 
+        if !missing.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Failed to export {} of {} environment variables from '{}' to '{}' after {} attempts: {}",
+                missing.len(),
+                env_vars.len(),
+                from_repo_name,
+                to_repo_name,
+                attempts_made,
+                missing.join(", ")
+            ));
+        }
+
         Ok(())
     }
 
     pub async fn start_pipeline(&self, repo_name: &str, branch: &str) -> Result<(), anyhow::Error> {
         let follow_resp = self.follow_project(repo_name, branch).await?;
+        tracing::debug!(?follow_resp, "followed {} project", repo_name);
+        if !follow_resp.following {
+            return Err(anyhow::anyhow!(
+                "Cannot follow {} project on CircleCI, pipeline was not started",
+                repo_name
+            ));
+        }
 
-        match follow_resp.first_build {
-            None => {
-                let url = format!(
-                    "https://circleci.com/api/v2/project/gh/{repo_name}/pipeline",
-                    repo_name = repo_name
-                );
-                let body = StartPipelineBody { branch };
-                let _: serde_json::Value = self.post(url, Some(body)).await?;
-                Ok(())
-            }
-            Some(_) => Ok(()),
+        // Following the project for the first time starts the pipeline on its own
+        if follow_resp.first_build == Some(true) {
+            return Ok(());
         }
+
+        let url = self.api_v2_url(&format!(
+            "project/gh/{repo_name}/pipeline",
+            repo_name = repo_name
+        ));
+        let body = StartPipelineBody { branch };
+        let _: serde_json::Value = self.post(url, Some(body)).await?;
+        Ok(())
     }
 
     async fn follow_project(
@@ -165,10 +216,10 @@ impl CircleCiApi {
         repo_name: &str,
         branch: &str,
     ) -> Result<FollowProjectResponse, Error> {
-        let url = format!(
-            "https://circleci.com/api/v1.1/project/gh/{repo_name}/follow",
+        let url = self.api_v1_url(&format!(
+            "project/gh/{repo_name}/follow",
             repo_name = repo_name
-        );
+        ));
         let body = FollowProjectBody { branch };
 
         let res: FollowProjectResponse = self.post(url, Some(body)).await?;
@@ -181,7 +232,7 @@ impl CircleCiApi {
         name: &str,
         vcs: VCSProvider,
     ) -> Result<Context, anyhow::Error> {
-        let url = "https://circleci.com/api/v2/context";
+        let url = self.api_v2_url("context");
         let body = CreateContextBody {
             name: name.to_string(),
             owner: ContextOwnerBody {
@@ -199,11 +250,11 @@ impl CircleCiApi {
         name: &str,
         value: &str,
     ) -> Result<ContextVariable, anyhow::Error> {
-        let url = format!(
-            "https://circleci.com/api/v2/context/{context_id}/environment-variable/{env_var_name}",
+        let url = self.api_v2_url(&format!(
+            "context/{context_id}/environment-variable/{env_var_name}",
             context_id = context_id,
             env_var_name = name
-        );
+        ));
         let body = UpdateContextVariableBody {
             value: value.to_string(),
         };
@@ -246,3 +297,120 @@ impl CircleCiApi {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn api(server: &MockServer) -> CircleCiApi {
+        CircleCiApi::new(&CircleCiConfig {
+            token: "token".to_string(),
+            bitbucket_org_id: "bitbucket-org".to_string(),
+            github_org_id: "github-org".to_string(),
+            base_url: server.uri(),
+        })
+    }
+
+    #[tokio::test]
+    async fn get_contexts_follows_page_tokens() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/context"))
+            .and(header(AUTH_HEADER, "token"))
+            .and(query_param("owner-id", "github-org"))
+            .and(query_param_is_missing("page-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{ "name": "first", "id": "1" }],
+                "next_page_token": "next",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/context"))
+            .and(query_param("owner-id", "github-org"))
+            .and(query_param("page-token", "next"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{ "name": "second", "id": "2" }],
+                "next_page_token": null,
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let contexts = api(&server)
+            .get_contexts(VCSProvider::GitHub)
+            .await
+            .unwrap();
+
+        let names = contexts.iter().map(|c| &c.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["first", "second"]);
+    }
+
+    #[tokio::test]
+    async fn start_pipeline_fails_when_project_is_not_followed() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1.1/project/gh/org/repo/follow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "following": false,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/project/gh/org/repo/pipeline"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({})))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let result = api(&server).start_pipeline("org/repo", "main").await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn start_pipeline_starts_pipeline_of_already_followed_project() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/v1.1/project/gh/org/repo/follow"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "following": true,
+                "first_build": false,
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/v2/project/gh/org/repo/pipeline"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        api(&server)
+            .start_pipeline("org/repo", "main")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_env_vars_returns_empty_list_when_project_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v2/project/bitbucket/workspace/missing/envvar"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let env_vars = api(&server)
+            .get_env_vars(VCSProvider::Bitbucket, "workspace/missing")
+            .await
+            .unwrap();
+
+        assert!(env_vars.is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 use std::{collections::HashSet, str::FromStr};
 
 use crate::circleci::config::raw::JobEntry;
+use crate::config::describe_yaml_error;
 use serde::{Deserialize, Serialize};
 
 use self::raw::Context;
@@ -14,21 +15,22 @@ impl FromStr for Config {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> anyhow::Result<Self> {
-        let raw = serde_yaml::from_str::<raw::Config>(s)?;
+        let mut value = serde_yaml::from_str::<serde_yaml::Value>(s)
+            .map_err(|e| anyhow::anyhow!(describe_yaml_error(s, &e)))?;
+        raw::apply_merge_keys(&mut value);
+        let raw = serde_yaml::from_value::<raw::Config>(value)?;
 
         let mut contexts = HashSet::<String>::new();
 
         raw.workflows
             .into_values()
-            .filter(|w| matches!(w, raw::WorkflowEntry::Workflow(_)))
             .flat_map(|w| match w {
                 raw::WorkflowEntry::Workflow(w) => w.jobs,
-                _ => unreachable!(),
+                raw::WorkflowEntry::Other(_) => vec![],
             })
-            .filter(|j| matches!(j, raw::JobEntry::Map(_)))
             .flat_map(|j| match j {
-                JobEntry::Map(map) => map.into_values().collect::<Vec<_>>(),
-                _ => unreachable!(),
+                JobEntry::Map(map) => map.into_values().flatten().collect::<Vec<_>>(),
+                JobEntry::Name(_) | JobEntry::Other(_) => vec![],
             })
             .flat_map(|j| j.context)
             .for_each(|c| match c {
@@ -40,6 +42,7 @@ impl FromStr for Config {
                         contexts.insert(c);
                     });
                 }
+                Context::Other(_) => {}
             });
 
         Ok(Config { contexts })
@@ -50,9 +53,42 @@ mod raw {
     use std::collections::BTreeMap;
 
     use serde::{Deserialize, Serialize};
+    use serde_yaml::Value;
+
+    /// Resolves YAML merge keys (`<<: *anchor`), which are not handled by `serde_yaml`.
+    ///
+    /// Keys defined directly in the mapping take precedence over the merged ones.
+    pub(crate) fn apply_merge_keys(value: &mut Value) {
+        match value {
+            Value::Mapping(mapping) => {
+                let merge_key = Value::String("<<".to_string());
+                if let Some(merged) = mapping.remove(&merge_key) {
+                    let sources = match merged {
+                        Value::Sequence(sources) => sources,
+                        source => vec![source],
+                    };
+                    for source in sources {
+                        if let Value::Mapping(source) = source {
+                            for (key, value) in source {
+                                if !mapping.contains_key(&key) {
+                                    mapping.insert(key, value);
+                                }
+                            }
+                        }
+                    }
+                }
+                for (_, value) in mapping.iter_mut() {
+                    apply_merge_keys(value);
+                }
+            }
+            Value::Sequence(sequence) => sequence.iter_mut().for_each(apply_merge_keys),
+            _ => {}
+        }
+    }
 
     #[derive(Debug, PartialEq, Serialize, Deserialize)]
     pub(crate) struct Config {
+        #[serde(default)]
         pub workflows: BTreeMap<String, WorkflowEntry>,
     }
 
@@ -71,12 +107,16 @@ mod raw {
     #[derive(Debug, PartialEq, Serialize, Deserialize)]
     #[serde(untagged)]
     pub(crate) enum JobEntry {
-        Map(BTreeMap<String, Job>),
+        /// Job with parameters, the parameters are empty (`null`) for jobs like `- build:`
+        Map(BTreeMap<String, Option<Job>>),
         Name(String),
+        /// Any other shape of the job, kept so a single unexpected job doesn't drop the whole workflow
+        Other(serde_yaml::Value),
     }
 
     #[derive(Debug, PartialEq, Serialize, Deserialize)]
     pub(crate) struct Job {
+        #[serde(default)]
         pub context: Option<Context>,
     }
 
@@ -85,5 +125,263 @@ mod raw {
     pub(crate) enum Context {
         String(String),
         Vec(Vec<String>),
+        Other(serde_yaml::Value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contexts(config: &str) -> Vec<String> {
+        let mut contexts = Config::from_str(config)
+            .expect("config should be parsed")
+            .contexts
+            .into_iter()
+            .collect::<Vec<_>>();
+        contexts.sort();
+        contexts
+    }
+
+    #[test]
+    fn collects_single_context_string() {
+        let config = r#"
+version: 2.1
+workflows:
+  build:
+    jobs:
+      - build:
+          context: org-global
+"#;
+
+        assert_eq!(contexts(config), vec!["org-global"]);
+    }
+
+    #[test]
+    fn collects_context_vector() {
+        let config = r#"
+version: 2.1
+workflows:
+  build:
+    jobs:
+      - build:
+          context:
+            - docker-hub
+            - org-global
+"#;
+
+        assert_eq!(contexts(config), vec!["docker-hub", "org-global"]);
+    }
+
+    #[test]
+    fn collects_contexts_from_multiple_workflows_once() {
+        let config = r#"
+version: 2.1
+workflows:
+  version: 2
+  commit:
+    jobs:
+      - test:
+          context: org-global
+  nightly:
+    triggers:
+      - schedule:
+          cron: "0 0 * * *"
+          filters:
+            branches:
+              only: main
+    jobs:
+      - test:
+          context: org-global
+      - e2e:
+          context: [org-global, browserstack]
+  release:
+    jobs:
+      - publish:
+          context: npm
+"#;
+
+        assert_eq!(contexts(config), vec!["browserstack", "npm", "org-global"]);
+    }
+
+    #[test]
+    fn returns_no_contexts_for_jobs_without_contexts() {
+        let config = r#"
+version: 2.1
+orbs:
+  node: circleci/node@5.0.2
+workflows:
+  test:
+    jobs:
+      - lint
+      - node/test
+      - build:
+      - deploy:
+          requires: [lint, build]
+          filters:
+            branches:
+              only: main
+"#;
+
+        assert!(contexts(config).is_empty());
+    }
+
+    #[test]
+    fn collects_contexts_from_mixed_job_forms() {
+        let config = r#"
+version: 2.1
+orbs:
+  node: circleci/node@5.0.2
+workflows:
+  version: 2
+  build-and-deploy:
+    jobs:
+      - lint
+      - node/test:
+          context: npm
+      - build:
+      - test:
+          matrix:
+            parameters:
+              node-version: ["14", "16"]
+          filters:
+            branches:
+              only: main
+          context:
+            - aws
+            - slack
+      - deploy:
+          requires: [build, test]
+          context: aws
+"#;
+
+        assert_eq!(contexts(config), vec!["aws", "npm", "slack"]);
+    }
+
+    #[test]
+    fn keeps_workflow_with_unexpected_job_shape() {
+        let config = r#"
+workflows:
+  main:
+    jobs:
+      - 42
+      - build:
+          context: [1, 2]
+      - deploy:
+          context: production
+"#;
+
+        assert_eq!(contexts(config), vec!["production"]);
+    }
+
+    #[test]
+    fn accepts_config_without_workflows() {
+        let config = r#"
+version: 2.1
+jobs:
+  build:
+    docker:
+      - image: cimg/base:stable
+"#;
+
+        assert!(contexts(config).is_empty());
+    }
+
+    #[test]
+    fn collects_contexts_from_config_with_orbs_and_commands() {
+        let config = r#"
+version: 2.1
+
+orbs:
+  aws-ecr: circleci/aws-ecr@8.1.2
+  slack: circleci/slack@4.10.1
+
+commands:
+  install:
+    steps:
+      - run: npm ci
+
+jobs:
+  test:
+    docker:
+      - image: cimg/node:16.15
+    steps:
+      - checkout
+      - install
+      - run: npm test
+
+workflows:
+  test-and-release:
+    jobs:
+      - test:
+          context: npm
+      - aws-ecr/build-and-push-image:
+          name: push-image
+          context:
+            - aws
+            - slack-notifications
+          repo: app
+          requires:
+            - test
+          filters:
+            branches:
+              only: main
+"#;
+
+        assert_eq!(contexts(config), vec!["aws", "npm", "slack-notifications"]);
+    }
+
+    #[test]
+    fn collects_contexts_from_config_with_anchors_and_aliases() {
+        let config = r#"
+version: 2.1
+
+defaults: &defaults
+  context: shared
+  filters:
+    branches:
+      only: [main, development]
+
+staging_context: &staging_context
+  - staging
+  - slack
+
+workflows:
+  deploy:
+    jobs:
+      - build:
+          <<: *defaults
+      - deploy-staging:
+          context: *staging_context
+      - deploy-production:
+          <<: *defaults
+          context: production
+"#;
+
+        assert_eq!(
+            contexts(config),
+            vec!["production", "shared", "slack", "staging"]
+        );
+    }
+
+    #[test]
+    fn collects_contexts_from_setup_config() {
+        let config = r#"
+version: 2.1
+setup: true
+
+orbs:
+  path-filtering: circleci/path-filtering@0.1.3
+
+workflows:
+  setup:
+    jobs:
+      - path-filtering/filter:
+          base-revision: main
+          config-path: .circleci/continue_config.yml
+          context: github
+"#;
+
+        assert_eq!(contexts(config), vec!["github"]);
     }
 }
@@ -1,27 +1,28 @@
 use anyhow::{anyhow, Context, Error};
+use std::path::Path;
 use std::path::PathBuf;
 use std::time::Instant;
-use std::{fs::File, path::Path};
 
-use crate::circleci::action::{describe_actions, Action, EnvVar};
+use crate::circleci::action::{describe_action_entries, Action, EnvVar};
 use crate::circleci::api;
 use crate::circleci::api::CircleCiApi;
 use crate::config::CircleCiConfig;
+use crate::migration_file::ActionEntry;
 use crate::prompts::Confirm;
-use crate::spinner;
+use crate::{migration_file, spinner};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Migration {
     version: String,
-    actions: Vec<Action>,
+    actions: Vec<ActionEntry<Action>>,
 }
 
 impl Migration {
     pub fn new(version: &str, actions: &[Action]) -> Self {
         Self {
             version: version.to_string(),
-            actions: actions.to_vec(),
+            actions: actions.iter().cloned().map(ActionEntry::new).collect(),
         }
     }
 }
@@ -30,6 +31,8 @@ pub struct Migrator {
     migration_file: PathBuf,
     version: String,
     circleci: CircleCiApi,
+    show_secrets: bool,
+    ignore_version: bool,
 }
 
 impl Migrator {
@@ -38,14 +41,28 @@ impl Migrator {
             migration_file: migration_file.to_path_buf(),
             version: version.to_owned(),
             circleci: CircleCiApi::new(&circleci_cfg),
+            show_secrets: false,
+            ignore_version: false,
         }
     }
 
+    /// Prints the values of context variables when describing the actions.
+    pub fn show_secrets(&mut self, show_secrets: bool) -> &mut Self {
+        self.show_secrets = show_secrets;
+        self
+    }
+
+    /// Applies the migration file even if it was generated by an incompatible version.
+    pub fn ignore_version(&mut self, ignore_version: bool) -> &mut Self {
+        self.ignore_version = ignore_version;
+        self
+    }
+
     pub async fn migrate(&self) -> anyhow::Result<()> {
         let migration = self.parse_migration_file()?;
 
         let actions = migration.actions;
-        println!("{}", describe_actions(&actions));
+        println!("{}", describe_action_entries(&actions, self.show_secrets));
 
         let confirmed = Confirm::with_prompt("Are you sure you want to migrate?").interact()?;
 
@@ -55,8 +72,8 @@ impl Migrator {
 
         let start = Instant::now();
 
-        for action in actions {
-            let _ = self.run(&action).await?;
+        for entry in actions.iter().filter(|entry| entry.enabled) {
+            self.run(&entry.action).await?;
         }
 
         let duration = start.elapsed();
@@ -66,14 +83,17 @@ impl Migrator {
     }
 
     fn parse_migration_file(&self) -> Result<Migration, Error> {
-        let file = File::open(&self.migration_file)?;
-        let migration: Migration = serde_json::from_reader(file).with_context(|| format!("Error when parsing {} file.\nIs this a JSON file?\nDoes the version match the program version ({})?\nConsider re-generating the migration file with `wizard` subcommand.", self.migration_file.display(), self.version))?;
-        if migration.version != self.version {
-            return Err(anyhow!("Migration file version is not compatible with current version, expected: {}, found: {}", self.version, migration.version));
+        let migration: Migration = migration_file::read(&self.migration_file).with_context(|| format!("Error when parsing {} file.\nIs this a JSON or YAML file?\nIs the version compatible with the program version ({})?\nConsider re-generating the migration file with `wizard` subcommand.", self.migration_file.display(), self.version))?;
+        if !self.ignore_version {
+            migration_file::check_version(&migration.version, &self.version)?;
         }
         Ok(migration)
     }
 
+    #[tracing::instrument(
+        skip_all,
+        fields(action = action.describe(false).lines().next().unwrap_or_default())
+    )]
     pub async fn run(&self, action: &Action) -> anyhow::Result<()> {
         match action {
             Action::CreateContext { name, variables } => self.create_context(name, variables).await,
@@ -89,6 +109,14 @@ impl Migrator {
                 repository_name,
                 branch,
             } => self.start_pipeline(repository_name, branch).await,
+            Action::UpdateContextVariables {
+                context_name,
+                variables,
+            } => self.update_context_variables(context_name, variables).await,
+            Action::SetProjectEnvironmentalVariables {
+                repository_name,
+                variables,
+            } => self.set_project_env_vars(repository_name, variables).await,
         }
     }
 
@@ -97,8 +125,7 @@ impl Migrator {
             "Starting pipeline for {} on branch {}",
             &repository_name, &branch
         ));
-        let _ = self
-            .circleci
+        self.circleci
             .start_pipeline(repository_name, branch)
             .await?;
         spinner.finish_with_message(format!(
@@ -115,29 +142,122 @@ impl Migrator {
         env_vars: &[String],
     ) -> Result<(), Error> {
         let spinner = spinner::create_spinner(format!("Moving {} environmental variables from '{}' project on Bitbucket to '{}' project on Github", env_vars.len(), &from_repository_name, &to_repository_name));
-        let _ = self
-            .circleci
+        self.circleci
             .export_environment(from_repository_name, to_repository_name, env_vars)
             .await?;
         spinner.finish_with_message(format!("Moved {} environmental variables from '{}' project on Bitbucket to '{}' project on Github", env_vars.len(), &from_repository_name, &to_repository_name));
         Ok(())
     }
 
+    async fn set_project_env_vars(
+        &self,
+        repository_name: &str,
+        variables: &[EnvVar],
+    ) -> Result<(), Error> {
+        for var in variables {
+            let spinner = spinner::create_spinner(format!(
+                "Setting '{}' variable in '{}' project",
+                &var.name, repository_name
+            ));
+            self.circleci
+                .create_project_env_var(
+                    api::VCSProvider::GitHub,
+                    repository_name,
+                    &var.name,
+                    &var.value,
+                )
+                .await?;
+            spinner.finish_with_message(format!("Set '{}' variable", &var.name));
+        }
+
+        Ok(())
+    }
+
+    async fn update_context_variables(
+        &self,
+        context_name: &str,
+        variables: &[EnvVar],
+    ) -> Result<(), Error> {
+        let spinner = spinner::create_spinner(format!("Fetching '{}' context", context_name));
+        let ctx = self
+            .circleci
+            .get_contexts(api::VCSProvider::GitHub)
+            .await?
+            .into_iter()
+            .find(|ctx| ctx.name == context_name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Context '{}' does not exist in GitHub organization",
+                    context_name
+                )
+            })?;
+        spinner.finish_with_message(format!("Found context '{}' (id: {})", &ctx.name, &ctx.id));
+
+        for var in variables {
+            let spinner = spinner::create_spinner(format!(
+                "Updating '{}' variable in '{}' context",
+                &var.name, context_name
+            ));
+            self.circleci
+                .add_context_variable(&ctx.id, &var.name, &var.value)
+                .await?;
+            spinner.finish_with_message(format!("Updated '{}' variable", &var.name));
+        }
+
+        Ok(())
+    }
+
     async fn create_context(&self, name: &str, variables: &[EnvVar]) -> Result<(), Error> {
         let spinner = spinner::create_spinner(format!("Creating '{}' context", name));
-        let ctx = self
+        let existing = self
             .circleci
-            .create_context(name, api::VCSProvider::GitHub)
-            .await?;
-        spinner.finish_with_message(format!("Created context '{}' (id: {})", &ctx.name, &ctx.id));
+            .get_contexts(api::VCSProvider::GitHub)
+            .await?
+            .into_iter()
+            .find(|ctx| ctx.name == name);
+
+        let (ctx, existing_variables) = match existing {
+            Some(ctx) => {
+                let existing_variables = self
+                    .circleci
+                    .get_context_variables(&ctx.id)
+                    .await?
+                    .into_iter()
+                    .map(|v| v.variable)
+                    .collect::<Vec<_>>();
+                spinner.finish_with_message(format!(
+                    "Context '{}' already exists (id: {}), reusing it",
+                    &ctx.name, &ctx.id
+                ));
+                (ctx, existing_variables)
+            }
+            None => {
+                let ctx = self
+                    .circleci
+                    .create_context(name, api::VCSProvider::GitHub)
+                    .await?;
+                spinner.finish_with_message(format!(
+                    "Created context '{}' (id: {})",
+                    &ctx.name, &ctx.id
+                ));
+                (ctx, vec![])
+            }
+        };
 
         for var in variables {
+            if existing_variables.contains(&var.name) {
+                println!(
+                    "Variable '{}' already exists in '{}' context, skipping",
+                    &var.name, &name
+                );
+                continue;
+            }
+
             let spinner = spinner::create_spinner(format!(
                 "Adding '{}' variable to '{}' context",
                 &var.name, &name
             ));
-            let _ = self
-                .circleci
+            self.circleci
                 .add_context_variable(&ctx.id, &var.name, &var.value)
                 .await?;
             spinner.finish_with_message(format!("Added '{}' variable", &var.name));
@@ -1,5 +1,5 @@
 mod action;
-mod api;
+pub(crate) mod api;
 mod config;
 mod migrator;
 mod wizard;
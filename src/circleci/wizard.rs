@@ -1,12 +1,11 @@
 use std::{
     collections::HashSet,
-    fs::File,
     path::{Path, PathBuf},
     str::FromStr,
 };
 
 use crate::prompts::{Confirm, FuzzySelect, Input, MultiSelect};
-use anyhow::{anyhow, Ok};
+use anyhow::{anyhow, Context as _, Ok};
 
 use crate::bitbucket::BitbucketApi;
 use crate::circleci::action::{Action, EnvVar};
@@ -16,7 +15,7 @@ use crate::{
     bitbucket,
     circleci::{api::Context, migrator::Migration},
     github::{FileContents, Repository, Team},
-    spinner,
+    migration_file, spinner,
 };
 
 use super::{api, config::Config};
@@ -27,6 +26,8 @@ pub struct Wizard {
     bitbucket: BitbucketApi,
     github: GithubApi,
     circleci: CircleCiApi,
+    config_path: Option<String>,
+    team_slug: Option<String>,
 }
 
 pub struct WizardResult {
@@ -42,9 +43,24 @@ impl Wizard {
             bitbucket: BitbucketApi::new(&config.bitbucket),
             github: GithubApi::new(&config.github),
             circleci: CircleCiApi::new(&config.circleci),
+            config_path: None,
+            team_slug: None,
         }
     }
 
+    /// Reads CircleCI config from the given path in the repositories,
+    /// instead of `.circleci/config.yml` or `.circleci/config.yaml`.
+    pub fn config_path(&mut self, config_path: &str) -> &mut Self {
+        self.config_path = Some(config_path.to_string());
+        self
+    }
+
+    /// Uses the GitHub team with the slug, skipping the interactive team selection.
+    pub fn team(&mut self, slug: &str) -> &mut Self {
+        self.team_slug = Some(slug.to_string());
+        self
+    }
+
     pub async fn run(&self) -> anyhow::Result<WizardResult> {
         println!("Welcome to CircleCi Migration Wizard!");
         let team = self.select_team().await?;
@@ -62,12 +78,16 @@ impl Wizard {
                 continue;
             }
 
-            let config = self.parse_config(&config.unwrap())?;
+            let config = self.parse_config(&repository.full_name, &config.unwrap())?;
 
             if let Some(move_envs_action) = self.move_env_vars(&repository).await? {
                 actions.push(move_envs_action);
             }
 
+            if let Some(set_envs_action) = Self::set_project_env_vars(&repository)? {
+                actions.push(set_envs_action);
+            }
+
             let defined_contexts = Self::contexts_to_be_created(&actions);
 
             let create_contexts_actions = self
@@ -75,6 +95,12 @@ impl Wizard {
                 .await?;
             actions.extend(create_contexts_actions);
 
+            let updated_contexts = Self::contexts_to_be_updated(&actions);
+            let update_contexts_actions = self
+                .update_contexts_actions(&config, &gh_contexts, &bb_contexts, &updated_contexts)
+                .await?;
+            actions.extend(update_contexts_actions);
+
             if let Some(start_build_action) = self.start_build(&repository).await? {
                 actions.push(start_build_action);
             }
@@ -102,6 +128,18 @@ impl Wizard {
         defined_contexts
     }
 
+    fn contexts_to_be_updated(actions: &[Action]) -> HashSet<String> {
+        actions
+            .iter()
+            .flat_map(|a| match a {
+                Action::UpdateContextVariables { context_name, .. } => {
+                    Some(context_name.to_owned())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
     async fn fetch_contexts(&self) -> anyhow::Result<(Vec<Context>, Vec<Context>)> {
         let spinner = spinner::create_spinner("Fetching GitHub contexts from CircleCI...");
         let gh_contexts = self.circleci.get_contexts(api::VCSProvider::GitHub).await?;
@@ -239,17 +277,17 @@ impl Wizard {
         )
         .default(true)
         .interact()?;
-        let action = if move_envs {
-            let env_vars = self.select_env_vars(&env_vars).await?;
-            let action = Action::MoveEnvironmentalVariables {
+        if !move_envs {
+            return Ok(None);
+        }
+
+        let action = self.select_env_vars(&env_vars).await?.map(|env_vars| {
+            Action::MoveEnvironmentalVariables {
                 from_repository_name: repository_name.clone(),
                 to_repository_name: repository.full_name.clone(),
                 env_vars,
-            };
-            Some(action)
-        } else {
-            None
-        };
+            }
+        });
 
         Ok(action)
     }
@@ -273,6 +311,21 @@ impl Wizard {
         let teams = self.github.get_teams().await?;
         spinner.finish_with_message(format!("Fetched {} teams", teams.len()));
 
+        if let Some(slug) = &self.team_slug {
+            return teams
+                .iter()
+                .find(|t| &t.slug == slug)
+                .cloned()
+                .ok_or_else(|| {
+                    let slugs = teams.iter().map(|t| t.slug.as_str()).collect::<Vec<_>>();
+                    anyhow!(
+                        "Team '{}' not found in GitHub, available teams: {}",
+                        slug,
+                        slugs.join(", ")
+                    )
+                });
+        }
+
         let team = FuzzySelect::with_prompt("Select team")
             .items(&teams)
             .default(0)
@@ -284,7 +337,9 @@ impl Wizard {
     async fn select_repositories(&self, team: &Team) -> anyhow::Result<Vec<Repository>> {
         let spinner =
             spinner::create_spinner(format!("Fetching repositories from {} team", &team.name));
-        let repositories = self.github.get_team_repositories(&team.slug).await?;
+        let mut repositories = self.github.get_team_repositories(&team.slug).await?;
+        // Sorting by name keeps repositories sharing a prefix (e.g. `project-api`, `project-web`) together
+        repositories.sort_by_key(|r| r.name.to_lowercase());
         spinner.finish_with_message("Fetched!");
         let selection =
             MultiSelect::with_prompt(format!("Select repositories from {} team", &team.name))
@@ -298,32 +353,73 @@ impl Wizard {
     }
 
     async fn check_config_exists(&self, repo: &Repository) -> anyhow::Result<Option<FileContents>> {
-        const CONFIG_PATH: &str = ".circleci/config.yml";
+        const CONFIG_PATHS: [&str; 2] = [".circleci/config.yml", ".circleci/config.yaml"];
+
+        let config_paths = match &self.config_path {
+            Some(config_path) => vec![config_path.as_str()],
+            None => CONFIG_PATHS.to_vec(),
+        };
 
         let spinner = spinner::create_spinner(format!("Checking {} config", &repo.name));
-        let config_file = self
-            .github
-            .get_file_contents(&repo.full_name, CONFIG_PATH)
-            .await;
-        match config_file {
-            Result::Ok(config_file) => {
+        for config_path in config_paths {
+            let config_file = self
+                .github
+                .get_file_contents(&repo.full_name, config_path)
+                .await?;
+            if let Some(config_file) = config_file {
                 spinner.finish_with_message(format!(
-                    "Found CircleCI config for {}, proceeding setup...",
-                    &repo.name
+                    "Found CircleCI config for {} ({}), proceeding setup...",
+                    &repo.name, config_path
                 ));
-                Ok(Some(config_file))
+                return Ok(Some(config_file));
             }
-            Err(_) => {
-                spinner.finish_with_message(format!(
-                    "No CircleCI config found for {}, skipping...",
-                    &repo.name
-                ));
-                Ok(None)
+        }
+
+        spinner.finish_with_message(format!(
+            "No CircleCI config found for {}, skipping...",
+            &repo.name
+        ));
+        Ok(None)
+    }
+
+    /// Asks for environment variables which should be set on the GitHub project with explicit values,
+    /// for the ones which are not carried over by moving the environment variables
+    fn set_project_env_vars(repository: &Repository) -> anyhow::Result<Option<Action>> {
+        let set_vars = Confirm::with_prompt(format!(
+            "Do you want to set environment variables with explicit values in '{}' project?",
+            &repository.name
+        ))
+        .default(false)
+        .interact()?;
+
+        if !set_vars {
+            return Ok(None);
+        }
+
+        let mut names = vec![];
+        loop {
+            let name = Input::with_prompt("Variable name (leave empty to finish):")
+                .allow_empty(true)
+                .interact()?;
+            if name.trim().is_empty() {
+                break;
             }
+            names.push(name.trim().to_string());
+        }
+
+        if names.is_empty() {
+            println!("No variables provided, skipping...");
+            return Ok(None);
         }
+
+        Ok(Some(Action::SetProjectEnvironmentalVariables {
+            repository_name: repository.full_name.clone(),
+            variables: Self::input_variables(names)?,
+        }))
     }
 
-    async fn select_env_vars(&self, env_vars: &[String]) -> anyhow::Result<Vec<String>> {
+    /// Returns `None` when no environment variables were selected, so moving them can be skipped
+    async fn select_env_vars(&self, env_vars: &[String]) -> anyhow::Result<Option<Vec<String>>> {
         let all = Confirm::with_prompt(
             "Do you want to move all environment variables? (No = select which to move)",
         )
@@ -331,16 +427,17 @@ impl Wizard {
         .interact()?;
 
         if all {
-            Ok(env_vars.to_vec())
+            Ok(Some(env_vars.to_vec()))
         } else {
             let selection = MultiSelect::with_prompt("Select environment variables to move")
                 .items(env_vars)
                 .interact()?;
             if selection.is_empty() {
-                println!("⚠️No environment variables selected");
+                println!("⚠️No environment variables selected, skipping...");
+                return Ok(None);
             }
             let env_vars: Vec<String> = selection.into_iter().cloned().collect::<Vec<_>>();
-            Ok(env_vars)
+            Ok(Some(env_vars))
         }
     }
 
@@ -429,17 +526,8 @@ impl Wizard {
                     &context
                 ));
 
-                let variables = variables
-                    .into_iter()
-                    .map(|variable| {
-                        let name = variable.variable;
-                        let value =
-                            Input::with_prompt(format!("Input value for '{}' variable:", name))
-                                .interact()
-                                .expect("invalid input for variable value");
-                        EnvVar { name, value }
-                    })
-                    .collect::<Vec<_>>();
+                let variables =
+                    Self::input_variables(variables.into_iter().map(|v| v.variable).collect())?;
                 actions.push(Action::CreateContext {
                     name: context,
                     variables,
@@ -458,6 +546,124 @@ impl Wizard {
         Ok(actions)
     }
 
+    /// Lets the user update variables of the contexts referenced in the config, which already exist in GitHub organization
+    async fn update_contexts_actions(
+        &self,
+        config: &Config,
+        gh_contexts: &[Context],
+        bb_contexts: &[Context],
+        updated_contexts: &HashSet<String>,
+    ) -> anyhow::Result<Vec<Action>> {
+        let mut existing = gh_contexts
+            .iter()
+            .filter(|c| config.contexts.contains(&c.name) && !updated_contexts.contains(&c.name))
+            .collect::<Vec<_>>();
+        existing.sort_by(|a, b| a.name.cmp(&b.name));
+
+        if existing.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let update = Confirm::with_prompt(format!(
+            "Do you want to update variables of existing contexts ({})?",
+            existing
+                .iter()
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+        .default(false)
+        .interact()?;
+
+        if !update {
+            return Ok(vec![]);
+        }
+
+        let names = existing.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        let selection = MultiSelect::with_prompt("Select contexts to update")
+            .items(&names)
+            .interact()?;
+
+        let mut actions = vec![];
+        for name in selection {
+            let gh_context = existing
+                .iter()
+                .find(|c| &c.name == name)
+                .expect("selected context exists");
+            let spinner = spinner::create_spinner(format!("Fetching {} context variables", name));
+            let gh_variables = self
+                .circleci
+                .get_context_variables(&gh_context.id)
+                .await?
+                .into_iter()
+                .map(|v| v.variable)
+                .collect::<HashSet<_>>();
+            let mut variables = match bb_contexts.iter().find(|c| &c.name == name) {
+                Some(bb_context) => self
+                    .circleci
+                    .get_context_variables(&bb_context.id)
+                    .await?
+                    .into_iter()
+                    .map(|v| v.variable)
+                    .collect::<HashSet<_>>(),
+                None => HashSet::new(),
+            };
+            variables.extend(gh_variables.iter().cloned());
+            let mut variables = variables.into_iter().collect::<Vec<_>>();
+            variables.sort();
+            spinner.finish_with_message(format!(
+                "Found {} variables for '{}' context",
+                variables.len(),
+                name
+            ));
+
+            if variables.is_empty() {
+                println!("No variables found for '{}' context, skipping...", name);
+                continue;
+            }
+
+            // Variables missing in the GitHub context are selected by default
+            let missing = variables
+                .iter()
+                .map(|v| !gh_variables.contains(v))
+                .collect::<Vec<_>>();
+            let selected = MultiSelect::with_prompt(format!(
+                "Select variables to update in '{}' context",
+                name
+            ))
+            .items(&variables)
+            .defaults(&missing)
+            .interact()?
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+
+            if selected.is_empty() {
+                println!("No variables selected, skipping...");
+                continue;
+            }
+
+            actions.push(Action::UpdateContextVariables {
+                context_name: name.clone(),
+                variables: Self::input_variables(selected)?,
+            });
+        }
+
+        Ok(actions)
+    }
+
+    fn input_variables(names: Vec<String>) -> anyhow::Result<Vec<EnvVar>> {
+        names
+            .into_iter()
+            .map(|name| {
+                let value = Input::with_prompt(format!("Input value for '{}' variable:", name))
+                    .password(true)
+                    .interact()?;
+                Ok(EnvVar { name, value })
+            })
+            .collect()
+    }
+
     async fn start_build(&self, repo: &Repository) -> anyhow::Result<Option<Action>> {
         let confirm = Confirm::with_prompt(format!(
             "Do you want to start a build for {} repository on CircleCI?",
@@ -509,11 +715,16 @@ impl Wizard {
         }))
     }
 
-    fn parse_config(&self, config: &FileContents) -> anyhow::Result<Config> {
-        let config = base64::decode_config(config.content.replace('\n', ""), base64::STANDARD)?;
+    fn parse_config(&self, repository_name: &str, file: &FileContents) -> anyhow::Result<Config> {
+        let config = file.decoded_content()?;
         let config = std::str::from_utf8(&config)?;
 
-        let config = Config::from_str(config)?;
+        let config = Config::from_str(config).with_context(|| {
+            format!(
+                "Cannot parse {} file of {} repository",
+                file.path, repository_name
+            )
+        })?;
 
         Ok(config)
     }
@@ -528,9 +739,7 @@ impl Wizard {
                 return Err(anyhow!("Migration file already exists"));
             }
         }
-        let mut file = File::create(&self.output)?;
-
-        serde_json::to_writer(&mut file, migration)?;
+        migration_file::write(&self.output, migration)?;
 
         Ok(())
     }
@@ -0,0 +1,86 @@
+use std::fmt::{Display, Formatter};
+
+use crate::api::ApiError;
+
+/// Category of the error, deciding about the exit code of the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Auth,
+    PartialMigration,
+    Config,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn of(err: &anyhow::Error) -> Self {
+        for cause in err.chain() {
+            if matches!(
+                cause.downcast_ref::<ApiError>(),
+                Some(ApiError::Unauthorized)
+            ) || cause.is::<AuthError>()
+            {
+                return ErrorKind::Auth;
+            }
+            if cause.is::<PartialMigrationError>() {
+                return ErrorKind::PartialMigration;
+            }
+            if cause.is::<ConfigError>() {
+                return ErrorKind::Config;
+            }
+        }
+
+        ErrorKind::Other
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Auth => 2,
+            ErrorKind::PartialMigration => 3,
+            ErrorKind::Config => 4,
+        }
+    }
+}
+
+/// Credentials were rejected or lack required permissions
+#[derive(Debug)]
+pub(crate) struct AuthError(pub(crate) String);
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+/// Configuration of the program is missing or invalid
+#[derive(Debug)]
+pub struct ConfigError(pub(crate) String);
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Some of the actions of the migration failed, while the others succeeded
+#[derive(Debug)]
+pub(crate) struct PartialMigrationError {
+    pub(crate) failed: usize,
+    pub(crate) total: usize,
+}
+
+impl Display for PartialMigrationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} repositories failed to migrate",
+            self.failed, self.total
+        )
+    }
+}
+
+impl std::error::Error for PartialMigrationError {}
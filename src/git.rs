@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::{Mutex, OnceLock};
+use std::{fs, fs::File};
+
+use anyhow::anyhow;
+use tempdir::TempDir;
+use tokio::signal::unix::{signal, SignalKind};
+
+/// Directories with SSH keys, which need to be wiped when the process gets interrupted
+static KEY_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Path of the `git` binary, `git` from `PATH` when not configured
+static GIT_BINARY: OnceLock<String> = OnceLock::new();
+
+/// Configures path of the `git` binary (or a wrapper of it) used for cloning and pushing the repositories.
+///
+/// Has to be called before any repository is cloned, otherwise `git` from `PATH` is used.
+pub fn configure_git_binary(path: &str) -> anyhow::Result<()> {
+    GIT_BINARY
+        .set(path.to_string())
+        .map_err(|_| anyhow!("git binary is already configured"))
+}
+
+fn git_binary() -> &'static str {
+    GIT_BINARY.get().map(|s| s.as_str()).unwrap_or("git")
+}
+
+/// Creates `git` command, using the configured binary
+pub(crate) fn command() -> Command {
+    Command::new(git_binary())
+}
+
+/// Temporary directory holding the SSH keys (and clones of the repositories).
+///
+/// The keys are zeroed before the directory is removed, which happens when the guard is dropped,
+/// or when the process receives SIGINT/SIGTERM (see [`cleanup_on_termination`]).
+pub(crate) struct KeysDir {
+    dir: TempDir,
+}
+
+impl KeysDir {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        let dir = TempDir::new("migrate-bb-to-gh")?;
+        KEY_DIRS
+            .lock()
+            .expect("key dirs lock poisoned")
+            .push(dir.path().to_path_buf());
+        Ok(Self { dir })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Stores the SSH key, together with an askpass script providing the `passphrase` of the key.
+    ///
+    /// Fails when the key is protected with a passphrase which isn't given, as `ssh` would wait for it.
+    pub(crate) fn store_ssh_key(
+        &self,
+        name: &str,
+        key: &str,
+        passphrase: Option<&str>,
+    ) -> anyhow::Result<PathBuf> {
+        let key_path = store_ssh_key(name, key, self.path())?;
+        match passphrase {
+            Some(passphrase) => store_askpass(&key_path, passphrase)?,
+            None if is_encrypted_key(key) => {
+                return Err(anyhow!(
+                    "The {} SSH key is protected with a passphrase, set `ssh_key_passphrase` in the git configuration (or use a key without a passphrase)",
+                    name
+                ))
+            }
+            None => {}
+        }
+        Ok(key_path)
+    }
+}
+
+impl Drop for KeysDir {
+    fn drop(&mut self) {
+        KEY_DIRS
+            .lock()
+            .expect("key dirs lock poisoned")
+            .retain(|path| path != self.dir.path());
+        let _ = wipe_dir(self.dir.path());
+    }
+}
+
+/// Waits for SIGINT/SIGTERM in the background, wiping all [`KeysDir`]s before exiting the process
+pub fn cleanup_on_termination() -> anyhow::Result<()> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = sigterm.recv() => {},
+            _ = sigint.recv() => {},
+        }
+        let dirs = KEY_DIRS.lock().map(|dirs| dirs.clone()).unwrap_or_default();
+        for dir in dirs {
+            let _ = wipe_dir(&dir);
+        }
+        std::process::exit(130);
+    });
+    Ok(())
+}
+
+/// Overwrites the files directly inside `dir` with zeros and removes the whole directory
+fn wipe_dir(dir: &Path) -> anyhow::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let mut perms = fs::metadata(&path)?.permissions();
+        perms.set_mode(0o600);
+        fs::set_permissions(&path, perms)?;
+
+        let len = fs::metadata(&path)?.len() as usize;
+        let mut file = fs::OpenOptions::new().write(true).open(&path)?;
+        file.write_all(&vec![0; len])?;
+        file.sync_all()?;
+    }
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+/// Runs the command like [`Command::output`], but when `progress` is given, lines of its stderr
+/// (e.g. progress of `git clone`) are passed to it as soon as they are printed.
+///
+/// The stderr is captured in both cases, so it can be shown when the command fails.
+pub(crate) fn output(
+    command: &mut Command,
+    progress: Option<&dyn Fn(&str)>,
+) -> std::io::Result<Output> {
+    let progress = match progress {
+        Some(progress) => progress,
+        None => return command.output(),
+    };
+
+    let mut child = command
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+
+    let mut captured = vec![];
+    let mut line = vec![];
+    let mut buf = [0; 1024];
+    loop {
+        let read = stderr.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        for &byte in &buf[..read] {
+            captured.push(byte);
+            // git updates progress lines with carriage returns
+            if byte == b'\r' || byte == b'\n' {
+                if !line.is_empty() {
+                    progress(String::from_utf8_lossy(&line).trim());
+                    line.clear();
+                }
+            } else {
+                line.push(byte);
+            }
+        }
+    }
+
+    Ok(Output {
+        status: child.wait()?,
+        stdout: vec![],
+        stderr: captured,
+    })
+}
+
+/// Stores the SSH key in a file readable only by the owner, as required by `ssh`
+pub(crate) fn store_ssh_key(name: &str, key: &str, path: &Path) -> anyhow::Result<PathBuf> {
+    let file_path = path.join(name);
+    let mut key_file = File::create(&file_path)?;
+    key_file.write_all(key.as_ref())?;
+
+    let mut perms = key_file.metadata()?.permissions();
+    perms.set_mode(0o400);
+    key_file.set_permissions(perms)?;
+
+    Ok(file_path)
+}
+
+/// Path of the askpass script providing the passphrase of the key
+fn askpass_path(key_path: &Path) -> PathBuf {
+    let mut file_name = key_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push("-askpass");
+    key_path.with_file_name(file_name)
+}
+
+/// Stores a script printing the passphrase, which `ssh` runs instead of asking for it in the terminal
+fn store_askpass(key_path: &Path, passphrase: &str) -> anyhow::Result<()> {
+    let file_path = askpass_path(key_path);
+    let mut script = File::create(&file_path)?;
+    write!(
+        script,
+        "#!/bin/sh\nprintf '%s\\n' '{}'\n",
+        passphrase.replace('\'', "'\\''")
+    )?;
+
+    let mut perms = script.metadata()?.permissions();
+    perms.set_mode(0o500);
+    script.set_permissions(perms)?;
+
+    Ok(())
+}
+
+/// Checks whether the key (in OpenSSH or PEM format) is encrypted with a passphrase
+fn is_encrypted_key(key: &str) -> bool {
+    if key.contains("Proc-Type: 4,ENCRYPTED") || key.contains("BEGIN ENCRYPTED PRIVATE KEY") {
+        return true;
+    }
+    if !key.contains("BEGIN OPENSSH PRIVATE KEY") {
+        return false;
+    }
+
+    let body = key
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with("-----"))
+        .collect::<String>();
+    // The binary key starts with the magic string, followed by length-prefixed name of the cipher
+    const MAGIC: &[u8] = b"openssh-key-v1\0";
+    match base64::decode(body) {
+        Ok(decoded) if decoded.starts_with(MAGIC) => {
+            let cipher = &decoded[MAGIC.len()..];
+            cipher.get(4..8) != Some(b"none".as_slice())
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn prepare_ssh_command(key_path: &Path) -> anyhow::Result<String> {
+    let mut cmd = format!(
+        "ssh -i '{private_key_file}' -o IdentitiesOnly=yes -o StrictHostKeyChecking=no -o UserKnownHostsFile='/dev/null' -F '/dev/null'",
+        private_key_file = fs::canonicalize(key_path)?.display()
+    );
+    let askpass = askpass_path(key_path);
+    if askpass.exists() {
+        // `SSH_ASKPASS_REQUIRE` makes `ssh` use the script even when it runs in a terminal
+        cmd = format!(
+            "SSH_ASKPASS='{askpass}' SSH_ASKPASS_REQUIRE=force DISPLAY=none {cmd}",
+            askpass = fs::canonicalize(askpass)?.display(),
+            cmd = cmd
+        );
+    }
+    Ok(cmd)
+}
+
+/// Verifies that `git` can be run, so the migration doesn't fail on the first clone with a confusing error.
+///
+/// With `verbose`, the error includes the `PATH` in which `git` was looked for.
+pub(crate) fn check_installed(verbose: bool) -> anyhow::Result<()> {
+    match command().arg("--version").output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(anyhow!(
+            "Cannot run git --version: {}\noutput: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let mut message = match git_binary() {
+                "git" => "git is required and was not found on PATH".to_string(),
+                binary => format!("git is required and was not found at {}", binary),
+            };
+            if verbose {
+                let path = std::env::var("PATH").unwrap_or_default();
+                message.push_str(&format!("\nPATH: {}", path));
+            }
+            Err(anyhow!(message))
+        }
+        Err(e) => Err(anyhow!("Cannot run git: {}", e)),
+    }
+}
+
+/// Checks whether the SSH key can be used to authenticate as `git` user to the given host
+pub(crate) fn check_ssh_access(host: &str, key_path: &Path) -> anyhow::Result<()> {
+    let ssh_command = prepare_ssh_command(key_path)?;
+    // Batch mode disables the askpass script too, which provides the passphrase without prompting anyway
+    let batch_mode = if askpass_path(key_path).exists() {
+        ""
+    } else {
+        "-o BatchMode=yes "
+    };
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} {}-T git@{}", ssh_command, batch_mode, host))
+        .output()?;
+
+    // Both GitHub and Bitbucket don't provide shell access, so the exit code
+    // is not meaningful, but they greet successfully authenticated users
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stdout.contains("authenticated") || stderr.contains("authenticated") {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Cannot authenticate to {}: {}",
+            host,
+            stderr.trim()
+        ))
+    }
+}
+
+/// Lists refs (with the commit they point to) of the repository stored in `repo_path`
+pub(crate) fn local_refs(repo_path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let output = command().arg("show-ref").current_dir(repo_path).output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Error when listing refs of {}: {}\noutput: {}",
+            repo_path.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(parse_refs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Deletes refs other than branches and tags (e.g. Bitbucket's `refs/pull-requests/*`)
+/// from the repository stored in `repo_path`, returning the number of deleted refs
+pub(crate) fn prune_refs(repo_path: &Path) -> anyhow::Result<usize> {
+    let output = command()
+        .args(["for-each-ref", "--format=%(refname)"])
+        .current_dir(repo_path)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Error when listing refs of {}: {}\noutput: {}",
+            repo_path.display(),
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let refs = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|r| !r.starts_with("refs/heads/") && !r.starts_with("refs/tags/"))
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
+
+    for r in &refs {
+        let output = command()
+            .args(["update-ref", "-d", r])
+            .current_dir(repo_path)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "Error when deleting {} ref: {}\noutput: {}",
+                r,
+                output.status,
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+    }
+
+    Ok(refs.len())
+}
+
+/// Lists refs (with the commit they point to) of the remote repository
+pub(crate) fn remote_refs(
+    repo_path: &Path,
+    remote_url: &str,
+    key_path: &Path,
+) -> anyhow::Result<HashMap<String, String>> {
+    let ssh_command = prepare_ssh_command(key_path)?;
+    let output = command()
+        .arg("-c")
+        .arg(format!("core.sshCommand={}", ssh_command))
+        .arg("ls-remote")
+        .arg(remote_url)
+        .current_dir(repo_path)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Error when listing refs of {}: {}\noutput: {}",
+            remote_url,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(parse_refs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_refs(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let sha = parts.next()?;
+            let name = parts.next()?;
+            Some((name.to_string(), sha.to_string()))
+        })
+        .collect()
+}
@@ -1,13 +1,15 @@
 use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
 use reqwest::IntoUrl;
-use std::fmt::{Display, Formatter};
+use std::fmt::Display;
 
-use crate::api::{ApiClient, BasicAuth};
+use crate::api::{http_client, ApiClient, ApiError, BasicAuth};
+use crate::cache;
 use crate::config::GitHubConfig;
+use crate::error::AuthError;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum TeamRepositoryPermission {
     Pull,
@@ -36,10 +38,29 @@ pub enum TeamPrivacy {
     Closed,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
 #[serde(rename_all = "snake_case")]
-enum RepositoryVisibility {
+pub enum RepositoryVisibility {
+    #[default]
     Private,
+    /// Visible to all members of the enterprise, available only for organizations owned by enterprise accounts
+    Internal,
+}
+
+impl Display for RepositoryVisibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepositoryVisibility::Private => write!(f, "private"),
+            RepositoryVisibility::Internal => write!(f, "internal"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Organization {
+    /// Returned only for organizations owned by enterprise accounts
+    #[serde(default)]
+    members_can_create_internal_repositories: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -61,14 +82,98 @@ struct CreateTeam {
     name: String,
     repo_names: Vec<String>,
     privacy: TeamPrivacy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_team_id: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct CreateRepository {
+#[derive(Serialize, Debug)]
+struct CreateRepository<'a> {
     name: String,
-    auto_init: bool,
     private: bool,
     visibility: RepositoryVisibility,
+    #[serde(flatten)]
+    metadata: &'a RepositoryMetadata,
+    #[serde(flatten)]
+    init: &'a RepositoryInit,
+}
+
+/// Initial commit of the created repository, which has to stay disabled when the repository is mirrored
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RepositoryInit {
+    /// Creates an initial commit with README
+    pub auto_init: bool,
+    /// Name of the `.gitignore` template (e.g. `Rust`) added in the initial commit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitignore_template: Option<String>,
+    /// Keyword of the license (e.g. `mit`) added in the initial commit
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license_template: Option<String>,
+}
+
+impl Display for RepositoryInit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "README")?;
+        if let Some(gitignore) = &self.gitignore_template {
+            write!(f, ", {} .gitignore", gitignore)?;
+        }
+        if let Some(license) = &self.license_template {
+            write!(f, ", {} license", license)?;
+        }
+        Ok(())
+    }
+}
+
+/// Details of the repository shown on its GitHub page
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct RepositoryMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+}
+
+impl RepositoryMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.description.is_none() && self.homepage.is_none()
+    }
+}
+
+/// Body of `422 Unprocessable Entity` responses
+#[derive(Deserialize, Debug)]
+struct ValidationFailed {
+    message: String,
+    #[serde(default)]
+    errors: Vec<ValidationError>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ValidationError {
+    #[serde(default)]
+    field: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+impl Display for ValidationFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        for error in &self.errors {
+            let description = error
+                .message
+                .as_deref()
+                .or(error.code.as_deref())
+                .unwrap_or("invalid");
+            match &error.field {
+                Some(field) => write!(f, "\n  - {}: {}", field, description)?,
+                None => write!(f, "\n  - {}", description)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -78,6 +183,49 @@ pub struct Repository {
     pub full_name: String,
     pub ssh_url: String,
     pub default_branch: String,
+    #[serde(default)]
+    pub permissions: Option<RepositoryPermissions>,
+    #[serde(default)]
+    pub html_url: Option<String>,
+}
+
+impl Repository {
+    /// URL of the repository page, constructed from the full name when not returned by the API
+    pub fn url(&self) -> String {
+        self.html_url
+            .clone()
+            .unwrap_or_else(|| format!("https://github.com/{}", self.full_name))
+    }
+}
+
+/// Permissions of the authenticated user (or team, when listing team repositories) to the repository
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RepositoryPermissions {
+    pub admin: bool,
+    #[serde(default)]
+    pub maintain: bool,
+    pub push: bool,
+    #[serde(default)]
+    pub triage: bool,
+    pub pull: bool,
+}
+
+impl RepositoryPermissions {
+    pub fn highest(&self) -> Option<TeamRepositoryPermission> {
+        if self.admin {
+            Some(TeamRepositoryPermission::Admin)
+        } else if self.maintain {
+            Some(TeamRepositoryPermission::Maintain)
+        } else if self.push {
+            Some(TeamRepositoryPermission::Push)
+        } else if self.triage {
+            Some(TeamRepositoryPermission::Triage)
+        } else if self.pull {
+            Some(TeamRepositoryPermission::Pull)
+        } else {
+            None
+        }
+    }
 }
 
 impl Display for Repository {
@@ -90,7 +238,41 @@ impl Display for Repository {
 pub struct FileContents {
     pub name: String,
     pub path: String,
+    pub sha: String,
     pub content: String,
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+#[cfg(feature = "circleci")]
+impl FileContents {
+    /// Decodes base64 content of the file, as returned by the Contents API.
+    ///
+    /// The content is split into lines (sometimes with CRLF line endings), and is not always
+    /// padded, so other base64 variants are tried when the standard one fails.
+    pub fn decoded_content(&self) -> anyhow::Result<Vec<u8>> {
+        let content = self.content.replace(['\r', '\n'], "");
+        let mut result = base64::decode_config(&content, base64::STANDARD);
+        for config in [
+            base64::STANDARD_NO_PAD,
+            base64::URL_SAFE,
+            base64::URL_SAFE_NO_PAD,
+        ] {
+            if result.is_ok() {
+                break;
+            }
+            if let Ok(decoded) = base64::decode_config(&content, config) {
+                result = Ok(decoded);
+            }
+        }
+        result.map_err(|e| anyhow::anyhow!("Cannot decode contents of {} file: {}", self.path, e))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Blob {
+    content: String,
+    encoding: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -99,7 +281,7 @@ pub struct Branch {
 }
 
 impl Display for Branch {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name)
     }
 }
@@ -110,6 +292,42 @@ pub struct Member {
     pub id: u32,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct RepositoryKey {
+    /// Public part of the key, without the comment
+    pub key: String,
+}
+
+#[derive(Serialize, Debug)]
+struct PutFileContentsBody<'a> {
+    message: &'a str,
+    content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha: Option<String>,
+}
+
+/// Merge settings of the repository
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RepositorySettings {
+    pub allow_squash_merge: bool,
+    pub allow_merge_commit: bool,
+    pub allow_rebase_merge: bool,
+    pub delete_branch_on_merge: bool,
+}
+
+impl Display for RepositorySettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "squash merge: {}, merge commit: {}, rebase merge: {}, delete head branches: {}",
+            self.allow_squash_merge,
+            self.allow_merge_commit,
+            self.allow_rebase_merge,
+            self.delete_branch_on_merge
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct SetDefaultBranchBody<'a> {
     pub default_branch: &'a str,
@@ -145,13 +363,93 @@ impl GithubApi {
         }
     }
 
+    pub fn organization_name(&self) -> &str {
+        &self.config.organization_name
+    }
+
+    /// Name of the repository owned by the organization (or the user, for user accounts),
+    /// as used in `/repos/{owner}/{repo}` paths.
+    ///
+    /// Names qualified with another owner (e.g. the Bitbucket workspace) keep only the repository part.
+    fn repository_full_name(&self, name: &str) -> String {
+        let name = name.rsplit('/').next().unwrap_or(name);
+        format!("{}/{}", self.config.organization_name, name)
+    }
+
+    /// Whether the repositories are migrated to a user account, which has no teams nor members
+    pub fn is_user_account(&self) -> bool {
+        self.config.user_account
+    }
+
+    /// Verifies that the token has all the scopes needed for the migration.
+    ///
+    /// Tokens which don't report their scopes (e.g. fine-grained tokens) are not verified.
+    pub async fn verify_token_scopes(&self) -> anyhow::Result<()> {
+        const ORG_REQUIRED_SCOPES: [&str; 2] = ["repo", "admin:org"];
+        const USER_REQUIRED_SCOPES: [&str; 1] = ["repo"];
+        let required_scopes: &[&str] = if self.config.user_account {
+            &USER_REQUIRED_SCOPES
+        } else {
+            &ORG_REQUIRED_SCOPES
+        };
+
+        let request =
+            self.build_common_parts(http_client().get(format!("{}/user", self.config.api_url)));
+        let response = request.send().await.map_err(ApiError::from)?;
+        if !response.status().is_success() {
+            let err = ApiError::from_response(response).await;
+            return Err(anyhow::Error::new(err).context("Failed to verify GitHub token"));
+        }
+
+        let scopes = match response.headers().get("x-oauth-scopes") {
+            Some(scopes) => scopes.to_str()?,
+            None => return Ok(()),
+        };
+        let scopes = scopes.split(',').map(|s| s.trim()).collect::<Vec<_>>();
+
+        let missing = required_scopes
+            .iter()
+            .filter(|scope| !scopes.contains(scope))
+            .copied()
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(AuthError(format!(
+                "GitHub token is missing required scopes: {}\nUpdate the token at https://github.com/settings/tokens",
+                missing.join(", ")
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
     pub async fn get_teams(&self) -> Result<Vec<Team>, anyhow::Error> {
+        self.fetch_teams(true).await
+    }
+
+    /// Fetches the teams bypassing the cache, to see the ones created after the list was cached
+    pub async fn get_current_teams(&self) -> Result<Vec<Team>, anyhow::Error> {
+        self.fetch_teams(false).await
+    }
+
+    async fn fetch_teams(&self, use_cache: bool) -> Result<Vec<Team>, anyhow::Error> {
+        if self.config.user_account {
+            return Ok(vec![]);
+        }
+
         let url = format!(
-            "https://api.github.com/orgs/{org_name}/teams",
+            "{api_url}/orgs/{org_name}/teams",
+            api_url = &self.config.api_url,
             org_name = &self.config.organization_name
         );
 
-        let res: Vec<Team> = self.get(url).await?;
+        let cache_key = format!("github-{}-teams", &self.config.organization_name);
+        let res: Vec<Team> = if use_cache {
+            cache::cached(&cache_key, self.get(url)).await?
+        } else {
+            self.get(url).await?
+        };
         let not_secret_teams: Vec<Team> = res
             .into_iter()
             .filter(|t| t.privacy != TeamPrivacy::Secret)
@@ -164,9 +462,12 @@ impl GithubApi {
         &self,
         name: &str,
         repositories: &[String],
+        description: Option<&str>,
+        parent_team_id: Option<u32>,
     ) -> Result<Team, anyhow::Error> {
         let url = format!(
-            "https://api.github.com/orgs/{org_name}/teams",
+            "{api_url}/orgs/{org_name}/teams",
+            api_url = &self.config.api_url,
             org_name = &self.config.organization_name
         );
 
@@ -174,6 +475,8 @@ impl GithubApi {
             name: name.to_string(),
             repo_names: repositories.iter().map(|r| r.to_string()).collect(),
             privacy: TeamPrivacy::Closed,
+            description: description.map(|d| d.to_string()),
+            parent_team_id,
         };
 
         let res: Team = self.post(url, Some(body)).await?;
@@ -188,10 +491,11 @@ impl GithubApi {
         repository_name: &str,
     ) -> Result<(), anyhow::Error> {
         let url = format!(
-            "https://api.github.com/orgs/{org_name}/teams/{team_slug}/repos/{repo_name}",
+            "{api_url}/orgs/{org_name}/teams/{team_slug}/repos/{repo_name}",
+            api_url = &self.config.api_url,
             team_slug = team_slug,
             org_name = &self.config.organization_name,
-            repo_name = repository_name
+            repo_name = self.repository_full_name(repository_name)
         );
 
         let _: Option<serde_json::Value> = self
@@ -201,51 +505,174 @@ impl GithubApi {
         Ok(())
     }
 
-    pub async fn create_repository(&self, name: &str) -> Result<Repository, anyhow::Error> {
+    pub async fn add_repository_collaborator(
+        &self,
+        full_repo_name: &str,
+        login: &str,
+        permission: &TeamRepositoryPermission,
+    ) -> anyhow::Result<()> {
         let url = format!(
-            "https://api.github.com/orgs/{org_name}/repos",
+            "{api_url}/repos/{repo_name}/collaborators/{login}",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(full_repo_name),
+            login = login
+        );
+
+        let _: Option<serde_json::Value> = self
+            .put(url, Some(serde_json::json!({ "permission": permission })))
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_deploy_keys(
+        &self,
+        full_repo_name: &str,
+    ) -> anyhow::Result<Vec<RepositoryKey>> {
+        let url_factory = |page: u32| {
+            format!(
+                "{api_url}/repos/{repo_name}/keys?per_page=100&page={page}",
+                api_url = &self.config.api_url,
+                repo_name = self.repository_full_name(full_repo_name),
+                page = &page
+            )
+        };
+
+        let keys = self.get_all_pages(url_factory).await?;
+
+        Ok(keys)
+    }
+
+    pub async fn add_deploy_key(
+        &self,
+        full_repo_name: &str,
+        title: &str,
+        key: &str,
+        read_only: bool,
+    ) -> anyhow::Result<()> {
+        let url = format!(
+            "{api_url}/repos/{repo_name}/keys",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(full_repo_name)
+        );
+
+        let _: serde_json::Value = self
+            .post(
+                url,
+                Some(serde_json::json!({ "title": title, "key": key, "read_only": read_only })),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Checks whether repositories with `internal` visibility can be created in the organization
+    pub async fn supports_internal_repositories(&self) -> anyhow::Result<bool> {
+        if self.config.user_account {
+            return Ok(false);
+        }
+
+        let url = format!(
+            "{api_url}/orgs/{org_name}",
+            api_url = &self.config.api_url,
             org_name = &self.config.organization_name
         );
 
+        let organization: Organization = self.get(url).await?;
+
+        Ok(organization
+            .members_can_create_internal_repositories
+            .is_some())
+    }
+
+    /// Creates the repository, returning the existing one (with the metadata updated) if the name is taken
+    pub async fn create_repository(
+        &self,
+        name: &str,
+        visibility: &RepositoryVisibility,
+        metadata: &RepositoryMetadata,
+        init: &RepositoryInit,
+    ) -> Result<Repository, anyhow::Error> {
+        let url = if self.config.user_account {
+            format!("{api_url}/user/repos", api_url = &self.config.api_url)
+        } else {
+            format!(
+                "{api_url}/orgs/{org_name}/repos",
+                api_url = &self.config.api_url,
+                org_name = &self.config.organization_name
+            )
+        };
+
         let body = CreateRepository {
             name: name.to_string(),
-            auto_init: false,
+            // Both private and internal repositories are hidden from the public
             private: true,
-            visibility: RepositoryVisibility::Private,
+            visibility: visibility.clone(),
+            metadata,
+            init,
         };
 
-        let res: Result<Repository, reqwest::Error> = self.post(url, Some(body)).await;
+        let res: Result<Repository, ApiError> = self.post(url, Some(body)).await;
 
         match res {
             Ok(r) => Ok(r),
-            Err(e) => {
-                if e.status() == Some(reqwest::StatusCode::UNPROCESSABLE_ENTITY) {
-                    let repo = self.get_repository(name).await?;
-                    Ok(repo)
-                } else {
-                    Err(anyhow::anyhow!("Failed to create repository: {}", e))
+            Err(ApiError::Unexpected { status, body })
+                if status == reqwest::StatusCode::UNPROCESSABLE_ENTITY =>
+            {
+                let validation: ValidationFailed = serde_json::from_str(&body).map_err(|_| {
+                    anyhow::anyhow!("Failed to create repository {}: {}", name, body)
+                })?;
+                if !validation.errors.iter().any(|e| {
+                    e.field.as_deref() == Some("name")
+                        && e.message
+                            .as_deref()
+                            .is_some_and(|m| m.contains("already exists"))
+                }) {
+                    return Err(anyhow::anyhow!(
+                        "Failed to create repository {}: {}",
+                        name,
+                        validation
+                    ));
+                }
+
+                let existing = self.get_repository(name).await?.ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Repository {} already exists, but it cannot be fetched",
+                        name
+                    )
+                })?;
+                if metadata.is_empty() {
+                    return Ok(existing);
                 }
+                // Keeps the metadata in sync on re-runs of the migration
+                self.update_repository_metadata(&existing.full_name, metadata)
+                    .await
+            }
+            Err(e) => {
+                Err(anyhow::Error::new(e).context(format!("Failed to create repository {}", name)))
             }
         }
     }
 
-    async fn get_repository(&self, name: &str) -> Result<Repository, anyhow::Error> {
+    pub async fn get_repository(&self, name: &str) -> anyhow::Result<Option<Repository>> {
         let url = format!(
-            "https://api.github.com/repos/{org_name}/{repo_name}",
-            org_name = &self.config.organization_name,
-            repo_name = name
+            "{api_url}/repos/{repo_name}",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(name)
         );
 
-        let res: Repository = self.get(url).await?;
-
-        Ok(res)
+        match self.get(url).await {
+            Ok(repo) => Ok(Some(repo)),
+            Err(ApiError::NotFound) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
-    #[cfg(feature = "circleci")]
     pub async fn get_team_repositories(&self, team_slug: &str) -> anyhow::Result<Vec<Repository>> {
         let url_factory = |page: u32| {
             format!(
-                "https://api.github.com/orgs/{org_name}/teams/{team_slug}/repos?page={page}",
+                "{api_url}/orgs/{org_name}/teams/{team_slug}/repos?per_page=100&page={page}",
+                api_url = &self.config.api_url,
                 org_name = &self.config.organization_name,
                 team_slug = team_slug,
                 page = page
@@ -257,26 +684,12 @@ impl GithubApi {
         Ok(res)
     }
 
-    pub async fn get_repositories(&self) -> anyhow::Result<Vec<Repository>> {
-        let url_factory = |page: u32| {
-            format!(
-                "https://api.github.com/orgs/{org_name}/repos?per_page=100&page={page}",
-                org_name = &self.config.organization_name,
-                page = page,
-            )
-        };
-
-        let res: Vec<Repository> = self.get_all_pages(url_factory).await?;
-
-        Ok(res)
-    }
-
-    #[cfg(feature = "circleci")]
     pub async fn get_repo_branches(&self, full_repo_name: &str) -> anyhow::Result<Vec<Branch>> {
         let url_factory = |page: u32| {
             format!(
-                "https://api.github.com/repos/{repo_name}/branches?per_page=100&page={page}",
-                repo_name = full_repo_name,
+                "{api_url}/repos/{repo_name}/branches?per_page=100&page={page}",
+                api_url = &self.config.api_url,
+                repo_name = self.repository_full_name(full_repo_name),
                 page = &page
             )
         };
@@ -286,26 +699,101 @@ impl GithubApi {
         Ok(branches)
     }
 
-    #[cfg(feature = "circleci")]
     pub async fn get_file_contents(
         &self,
         full_repo_name: &str,
         path: &str,
-    ) -> anyhow::Result<FileContents> {
+    ) -> anyhow::Result<Option<FileContents>> {
         let url = format!(
-            "https://api.github.com/repos/{repo}/contents/{path}",
-            repo = full_repo_name,
+            "{api_url}/repos/{repo}/contents/{path}",
+            api_url = &self.config.api_url,
+            repo = self.repository_full_name(full_repo_name),
             path = path
         );
 
-        let res = self.get(url).await?;
+        let res = self.get(url).await;
+
+        let mut file: FileContents = match res {
+            Ok(file) => file,
+            Err(ApiError::NotFound) => return Ok(None),
+            Err(err) => {
+                return Err(anyhow::anyhow!(
+                    "Failed to get {} file from {} repository: {}",
+                    path,
+                    full_repo_name,
+                    err
+                ))
+            }
+        };
 
-        Ok(res)
+        // Contents API doesn't return the content of files larger than 1 MB,
+        // those have to be fetched using the Git Blobs API
+        if file.content.is_empty() || file.encoding.as_deref() == Some("none") {
+            let blob = self.get_blob(full_repo_name, &file.sha).await?;
+            file.content = blob.content;
+            file.encoding = Some(blob.encoding);
+        }
+
+        Ok(Some(file))
+    }
+
+    async fn get_blob(&self, full_repo_name: &str, sha: &str) -> anyhow::Result<Blob> {
+        let url = format!(
+            "{api_url}/repos/{repo}/git/blobs/{sha}",
+            api_url = &self.config.api_url,
+            repo = self.repository_full_name(full_repo_name),
+            sha = sha
+        );
+
+        let blob: Blob = self.get(url).await?;
+
+        if blob.encoding != "base64" {
+            return Err(anyhow::anyhow!(
+                "Unsupported encoding of {} blob: {}",
+                sha,
+                blob.encoding
+            ));
+        }
+
+        Ok(blob)
+    }
+
+    /// Creates the file in default branch of the repository, or replaces its contents when it already exists
+    pub async fn put_file_contents(
+        &self,
+        full_repo_name: &str,
+        path: &str,
+        content: &str,
+        message: &str,
+    ) -> anyhow::Result<()> {
+        let existing = self.get_file_contents(full_repo_name, path).await?;
+
+        let url = format!(
+            "{api_url}/repos/{repo}/contents/{path}",
+            api_url = &self.config.api_url,
+            repo = self.repository_full_name(full_repo_name),
+            path = path
+        );
+        let body = PutFileContentsBody {
+            message,
+            content: base64::encode(content),
+            sha: existing.map(|f| f.sha),
+        };
+
+        let _: serde_json::Value = self.put(url, Some(body)).await.map_err(|e| {
+            anyhow::Error::new(e).context(format!(
+                "Failed to put {} file to {} repository",
+                path, full_repo_name
+            ))
+        })?;
+
+        Ok(())
     }
 
     pub async fn get_org_members(&self) -> Result<Vec<Member>, anyhow::Error> {
         let url = format!(
-            "https://api.github.com/orgs/{org_name}/members?per_page=100",
+            "{api_url}/orgs/{org_name}/members?per_page=100",
+            api_url = &self.config.api_url,
             org_name = &self.config.organization_name
         );
 
@@ -320,10 +808,25 @@ impl GithubApi {
         default_branch: &str,
     ) -> anyhow::Result<Repository> {
         let url = format!(
-            "https://api.github.com/repos/{repo_name}",
-            repo_name = full_repo_name
+            "{api_url}/repos/{repo_name}",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(full_repo_name)
         );
 
+        let branches = self.get_repo_branches(full_repo_name).await?;
+        if !branches.iter().any(|b| b.name == default_branch) {
+            return Err(anyhow::anyhow!(
+                "Cannot set '{}' as default branch of '{}' repository, the branch does not exist on GitHub.\nAvailable branches: {}",
+                default_branch,
+                full_repo_name,
+                branches
+                    .iter()
+                    .map(|b| b.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
         let body = SetDefaultBranchBody { default_branch };
 
         let res = self.patch(url, Some(body)).await?;
@@ -331,13 +834,84 @@ impl GithubApi {
         Ok(res)
     }
 
+    pub async fn update_repository_settings(
+        &self,
+        full_repo_name: &str,
+        settings: &RepositorySettings,
+    ) -> anyhow::Result<Repository> {
+        let url = format!(
+            "{api_url}/repos/{repo_name}",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(full_repo_name)
+        );
+
+        let res = self.patch(url, Some(settings)).await?;
+
+        Ok(res)
+    }
+
+    pub async fn update_repository_metadata(
+        &self,
+        full_repo_name: &str,
+        metadata: &RepositoryMetadata,
+    ) -> anyhow::Result<Repository> {
+        let url = format!(
+            "{api_url}/repos/{repo_name}",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(full_repo_name)
+        );
+
+        let res = self.patch(url, Some(metadata)).await?;
+
+        Ok(res)
+    }
+
+    pub async fn rename_repository(
+        &self,
+        full_repo_name: &str,
+        new_name: &str,
+    ) -> anyhow::Result<Repository> {
+        let url = format!(
+            "{api_url}/repos/{repo_name}",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(full_repo_name)
+        );
+
+        let res = self
+            .patch(url, Some(serde_json::json!({ "name": new_name })))
+            .await?;
+
+        Ok(res)
+    }
+
+    pub async fn rename_branch(
+        &self,
+        full_repo_name: &str,
+        branch: &str,
+        new_name: &str,
+    ) -> anyhow::Result<Branch> {
+        let url = format!(
+            "{api_url}/repos/{repo_name}/branches/{branch}/rename",
+            api_url = &self.config.api_url,
+            repo_name = self.repository_full_name(full_repo_name),
+            branch = branch
+        );
+
+        let res = self
+            .post(url, Some(serde_json::json!({ "new_name": new_name })))
+            .await?;
+
+        Ok(res)
+    }
+
     pub(crate) async fn update_team_membership(
         &self,
         team_slug: &str,
         member_login: &str,
     ) -> anyhow::Result<()> {
         let url = format!(
-            "https://api.github.com/orgs/{org}/teams/{team_slug}/memberships/{username}",
+            "{api_url}/orgs/{org}/teams/{team_slug}/memberships/{username}",
+            api_url = &self.config.api_url,
             org = &self.config.organization_name,
             team_slug = team_slug,
             username = member_login,
@@ -377,7 +951,7 @@ impl GithubApi {
 }
 
 impl ApiClient for GithubApi {
-    fn basic_auth(&self) -> Option<BasicAuth> {
+    fn basic_auth(&self) -> Option<BasicAuth<'_>> {
         Some(BasicAuth::new(&self.config.username, &self.config.password))
     }
 
@@ -391,3 +965,301 @@ impl ApiClient for GithubApi {
         Some(headers)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{body_json, body_partial_json, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn api(server: &MockServer) -> GithubApi {
+        GithubApi::new(&GitHubConfig {
+            username: "user".to_string(),
+            password: "token".to_string(),
+            organization_name: "org".to_string(),
+            user_account: false,
+            api_url: server.uri(),
+        })
+    }
+
+    fn repository(name: &str) -> serde_json::Value {
+        json!({
+            "id": 1,
+            "name": name,
+            "full_name": format!("org/{}", name),
+            "ssh_url": format!("git@github.com:org/{}.git", name),
+            "default_branch": "main",
+        })
+    }
+
+    #[tokio::test]
+    async fn get_team_repositories_fetches_all_pages() {
+        let server = MockServer::start().await;
+        let pages = [
+            json!([repository("first"), repository("second")]),
+            json!([repository("third")]),
+            json!([]),
+        ];
+        for (idx, page) in pages.iter().enumerate() {
+            Mock::given(method("GET"))
+                .and(path("/orgs/org/teams/developers/repos"))
+                .and(query_param("page", (idx + 1).to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+
+        let repositories = api(&server)
+            .get_team_repositories("developers")
+            .await
+            .unwrap();
+
+        let names = repositories.iter().map(|r| &r.name).collect::<Vec<_>>();
+        assert_eq!(names, vec!["first", "second", "third"]);
+    }
+
+    #[tokio::test]
+    async fn get_repository_returns_none_when_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let repository = api(&server).get_repository("missing").await.unwrap();
+
+        assert!(repository.is_none());
+    }
+
+    #[tokio::test]
+    async fn get_repository_reports_undecodable_response() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/broken"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("<html></html>"))
+            .mount(&server)
+            .await;
+
+        let err = api(&server).get_repository("broken").await.unwrap_err();
+
+        let message = err.to_string();
+        assert!(
+            message.starts_with(&format!(
+                "cannot decode response from {}/repos/org/broken: ",
+                server.uri()
+            )),
+            "{}",
+            message
+        );
+        assert!(err.source().is_some());
+    }
+
+    #[tokio::test]
+    async fn create_team_sets_parent_team() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/orgs/org/teams"))
+            .and(body_partial_json(json!({
+                "name": "Backend",
+                "parent_team_id": 7,
+            })))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({
+                "id": 8,
+                "name": "Backend",
+                "slug": "backend",
+                "privacy": "closed",
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let team = api(&server)
+            .create_team("Backend", &[], None, Some(7))
+            .await
+            .unwrap();
+
+        assert_eq!(team.slug, "backend");
+    }
+
+    #[tokio::test]
+    async fn create_repository_returns_existing_repository() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/orgs/org/repos"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "message": "Repository creation failed.",
+                "errors": [{
+                    "resource": "Repository",
+                    "code": "custom",
+                    "field": "name",
+                    "message": "name already exists on this account"
+                }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/existing"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(repository("existing")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let repository = api(&server)
+            .create_repository(
+                "existing",
+                &RepositoryVisibility::Private,
+                &RepositoryMetadata::default(),
+                &RepositoryInit::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(repository.full_name, "org/existing");
+    }
+
+    #[tokio::test]
+    async fn create_repository_updates_metadata_of_existing_repository() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/orgs/org/repos"))
+            .and(body_partial_json(json!({ "description": "Backend" })))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "message": "Repository creation failed.",
+                "errors": [{
+                    "resource": "Repository",
+                    "code": "custom",
+                    "field": "name",
+                    "message": "name already exists on this account"
+                }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/existing"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(repository("existing")))
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .and(path("/repos/org/existing"))
+            .and(body_json(json!({ "description": "Backend" })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(repository("existing")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let metadata = RepositoryMetadata {
+            description: Some("Backend".to_string()),
+            homepage: None,
+        };
+        let repository = api(&server)
+            .create_repository(
+                "existing",
+                &RepositoryVisibility::Private,
+                &metadata,
+                &RepositoryInit::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(repository.full_name, "org/existing");
+    }
+
+    #[tokio::test]
+    async fn create_repository_keeps_unauthorized_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/orgs/org/repos"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+
+        let err = api(&server)
+            .create_repository(
+                "api",
+                &RepositoryVisibility::Private,
+                &RepositoryMetadata::default(),
+                &RepositoryInit::default(),
+            )
+            .await
+            .unwrap_err();
+
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Auth);
+    }
+
+    #[tokio::test]
+    async fn create_repository_fails_on_other_validation_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/orgs/org/repos"))
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "message": "Validation Failed",
+                "errors": [{
+                    "resource": "Repository",
+                    "code": "invalid",
+                    "field": "visibility",
+                    "message": "visibility is invalid"
+                }]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/org/invalid"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(repository("invalid")))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = api(&server)
+            .create_repository(
+                "invalid",
+                &RepositoryVisibility::Private,
+                &RepositoryMetadata::default(),
+                &RepositoryInit::default(),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("visibility is invalid"));
+    }
+
+    #[tokio::test]
+    async fn repository_paths_use_user_account_as_owner() {
+        let server = MockServer::start().await;
+        Mock::given(method("PATCH"))
+            .and(path("/repos/user/api"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(repository("api")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/repos/user/api/keys"))
+            .respond_with(ResponseTemplate::new(201).set_body_json(json!({ "id": 1 })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let api = GithubApi::new(&GitHubConfig {
+            user_account: true,
+            organization_name: "user".to_string(),
+            ..api(&server).config
+        });
+
+        // Names from the migration file are qualified with the Bitbucket workspace
+        let settings = RepositorySettings {
+            allow_squash_merge: true,
+            allow_merge_commit: false,
+            allow_rebase_merge: false,
+            delete_branch_on_merge: true,
+        };
+        api.update_repository_settings("acme/api", &settings)
+            .await
+            .unwrap();
+        api.add_deploy_key("acme/api", "deploy", "ssh-ed25519 AAAA", true)
+            .await
+            .unwrap();
+    }
+}
@@ -1,10 +1,26 @@
 mod api;
 mod bitbucket;
+mod cache;
+pub mod check;
 pub mod config;
+pub mod error;
+mod git;
 mod github;
+pub mod list;
+mod migration_file;
 pub mod prompts;
+mod redact;
 pub mod repositories;
 mod spinner;
+pub mod teams_file;
+mod trace;
+pub mod user_map;
 
 #[cfg(feature = "circleci")]
 pub mod circleci;
+
+pub use api::configure_http_client;
+pub use cache::configure_cache;
+pub use git::{cleanup_on_termination, configure_git_binary};
+pub use redact::redact;
+pub use trace::configure_tracing;
@@ -0,0 +1,86 @@
+use crate::bitbucket::BitbucketApi;
+use crate::config::BitbucketConfig;
+
+#[derive(clap::ArgEnum, Clone, Debug)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// Prints all the projects from the Bitbucket workspace
+pub async fn list_projects(config: &BitbucketConfig, format: &OutputFormat) -> anyhow::Result<()> {
+    let bitbucket = BitbucketApi::new(config);
+    let projects = bitbucket.get_projects().await?;
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&projects)?),
+        OutputFormat::Table => {
+            let rows = projects
+                .iter()
+                .map(|p| vec![p.key.clone(), p.name.clone()])
+                .collect::<Vec<_>>();
+            print_table(&["KEY", "NAME"], &rows);
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints all the repositories from the Bitbucket project
+pub async fn list_repositories(
+    config: &BitbucketConfig,
+    project_key: &str,
+    format: &OutputFormat,
+) -> anyhow::Result<()> {
+    let bitbucket = BitbucketApi::new(config);
+    let repositories = bitbucket.get_project_repositories(project_key).await?;
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&repositories)?),
+        OutputFormat::Table => {
+            let rows = repositories
+                .iter()
+                .map(|r| {
+                    vec![
+                        r.full_name.clone(),
+                        r.name.clone(),
+                        r.main_branch.name.clone(),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            print_table(&["FULL NAME", "NAME", "MAIN BRANCH"], &rows);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let widths = headers
+        .iter()
+        .enumerate()
+        .map(|(idx, header)| {
+            rows.iter()
+                .map(|row| row[idx].chars().count())
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(|c| c.as_str()).collect()));
+    }
+}
@@ -1,10 +1,17 @@
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
 
 use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 #[cfg(feature = "circleci")]
 use migrate_bb_to_gh::circleci;
-use migrate_bb_to_gh::config;
+use migrate_bb_to_gh::error::ErrorKind;
+use migrate_bb_to_gh::list::OutputFormat;
 use migrate_bb_to_gh::repositories::{self, Migrator, Wizard};
+use migrate_bb_to_gh::teams_file::TeamsFile;
+use migrate_bb_to_gh::user_map::UserMap;
+use migrate_bb_to_gh::{check, config, list};
 
 /// Utility tool for migration of repositories from Bitbucket to GitHub for organizations
 #[derive(Parser)]
@@ -13,6 +20,30 @@ use migrate_bb_to_gh::repositories::{self, Migrator, Wizard};
 struct Cli {
     #[clap(subcommand)]
     command: Commands,
+    /// GitHub organization to migrate to, instead of the one from the configuration file
+    #[clap(long, global = true, value_name = "ORG")]
+    organization: Option<String>,
+    /// Migrate to the GitHub user account (named by --organization or the configuration file) instead of an organization
+    #[clap(long, global = true)]
+    user_account: bool,
+    /// Bitbucket workspace to migrate from, instead of the one from the configuration file
+    #[clap(long, global = true, value_name = "WORKSPACE")]
+    workspace: Option<String>,
+    /// Timeout (in seconds) of every request made to Bitbucket, GitHub and CircleCI APIs
+    #[clap(long, global = true, value_name = "SECS", default_value_t = 60)]
+    timeout: u64,
+    /// Cache the fetched lists of projects, teams and repositories on disk
+    #[clap(long, global = true)]
+    cache: bool,
+    /// Time (in seconds) after which the cached lists are fetched again
+    #[clap(long, global = true, value_name = "SECS", default_value_t = 3600)]
+    cache_ttl: u64,
+    /// Fetch the lists again, ignoring the cached ones (implies --cache)
+    #[clap(long, global = true)]
+    refresh: bool,
+    /// Print timings of the API requests, git commands and actions (filtered with RUST_LOG when set)
+    #[clap(long, global = true)]
+    trace: bool,
 }
 
 #[derive(Subcommand)]
@@ -27,12 +58,128 @@ enum Commands {
             default_value = "migration.json"
         )]
         output: PathBuf,
+        /// Select all repositories of the chosen project instead of picking them one by one
+        #[clap(long, conflicts_with_all = &["repos-file", "from"])]
+        all_repositories: bool,
+        /// Path to CSV or JSON file mapping Bitbucket usernames to GitHub logins
+        #[clap(long, parse(from_os_str), value_name = "PATH")]
+        user_map: Option<PathBuf>,
+        /// Path to JSON or YAML file with the teams to create (or update), with their members,
+        /// repositories and permission, used instead of asking for the teams
+        #[clap(long, parse(from_os_str), value_name = "PATH")]
+        teams_file: Option<PathBuf>,
+        /// Path to file with newline- or comma-separated `workspace/repo` names to migrate,
+        /// used instead of selecting the project and repositories
+        #[clap(long, parse(from_os_str), value_name = "PATH")]
+        repos_file: Option<PathBuf>,
+        /// Rename the selected default branches to this name (e.g. `main`) during the migration
+        #[clap(long, value_name = "BRANCH")]
+        default_branch_name: Option<String>,
+        /// Skip repositories not updated since the date (in YYYY-MM-DD format)
+        #[clap(
+            long,
+            value_name = "DATE",
+            validator = validate_date,
+            conflicts_with_all = &["repos-file", "from"]
+        )]
+        since: Option<String>,
+        /// Only list repositories of the project with names matching the regular expression
+        #[clap(
+            long,
+            value_name = "PATTERN",
+            validator = validate_regex,
+            conflicts_with_all = &["repos-file", "from"]
+        )]
+        filter: Option<String>,
+        /// Skip repositories of the project with names matching the regular expression (applied after --filter)
+        #[clap(
+            long,
+            value_name = "PATTERN",
+            validator = validate_regex,
+            conflicts_with_all = &["repos-file", "from"]
+        )]
+        exclude: Option<String>,
+        /// Start with the repositories chosen in the wizard which generated the migration file
+        #[clap(
+            long,
+            parse(from_os_str),
+            value_name = "MIGRATION_FILE",
+            conflicts_with = "repos-file"
+        )]
+        from: Option<PathBuf>,
+        /// Keep the Bitbucket default branches of the repositories, without asking for the new ones
+        #[clap(long)]
+        keep_source_default: bool,
+        /// Key of the Bitbucket project to migrate the repositories from, instead of selecting it
+        #[clap(
+            long,
+            value_name = "KEY",
+            conflicts_with_all = &["repos-file", "from"]
+        )]
+        project: Option<String>,
     },
     /// Migrates repositories from Bitbucket to GitHub, following the actions defined in migration file
     Migrate {
         /// Path to migration file
         #[clap(parse(from_os_str), value_name = "MIGRATION_FILE")]
         migration_file: PathBuf,
+        /// Apply the migration file even if it was generated by an incompatible version
+        #[clap(long)]
+        ignore_version: bool,
+        /// Verify that all refs were pushed to GitHub after mirroring each repository
+        #[clap(long)]
+        verify: bool,
+        /// Delete refs other than branches and tags (e.g. pull requests) before pushing mirrors
+        #[clap(long)]
+        prune_refs: bool,
+        /// Show progress of git commands while cloning and pushing the repositories
+        #[clap(short, long)]
+        verbose: bool,
+        /// Push the mirrors even if it rewrites or deletes refs already existing in GitHub repositories
+        #[clap(long)]
+        force_overwrite: bool,
+        /// Continue with the next actions when some of the repositories failed to migrate
+        #[clap(long)]
+        continue_on_error: bool,
+        /// Shell command to run before the migration starts
+        #[clap(long, value_name = "CMD")]
+        pre_hook: Option<String>,
+        /// Shell command to run after the migration, with MIGRATION_STATUS set to success or failure
+        #[clap(long, value_name = "CMD")]
+        post_hook: Option<String>,
+        /// URL to POST a JSON summary to when the migration ends (e.g. Slack incoming webhook)
+        #[clap(long, value_name = "URL")]
+        notify_webhook: Option<String>,
+        /// Path to a Markdown file where the report of the migration should be written
+        #[clap(long, parse(from_os_str), value_name = "PATH")]
+        report_md: Option<PathBuf>,
+    },
+    /// Lets you drop and reorder actions of the migration file
+    Edit {
+        /// Path to migration file
+        #[clap(parse(from_os_str), value_name = "MIGRATION_FILE")]
+        migration_file: PathBuf,
+    },
+    /// Checks whether repositories and teams from the migration file exist on GitHub
+    Status {
+        /// Path to migration file
+        #[clap(parse(from_os_str), value_name = "MIGRATION_FILE")]
+        migration_file: PathBuf,
+    },
+    /// Verifies credentials and connectivity of Bitbucket, GitHub (and CircleCI) integrations
+    Check,
+    /// Lists projects and repositories from Bitbucket workspace
+    List {
+        #[clap(subcommand)]
+        command: ListCommands,
+        /// Format of the output
+        #[clap(long, arg_enum, default_value = "table", global = true)]
+        format: OutputFormat,
+    },
+    /// Prints completion script of the shell to standard output
+    Completions {
+        #[clap(arg_enum, value_name = "SHELL")]
+        shell: Shell,
     },
     #[cfg(feature = "circleci")]
     /// Tool for migrating CircleCI configuration
@@ -43,6 +190,18 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum ListCommands {
+    /// Lists all projects from Bitbucket workspace
+    Projects,
+    /// Lists all repositories from Bitbucket project
+    Repos {
+        /// Key of the Bitbucket project
+        #[clap(value_name = "PROJECT_KEY")]
+        project_key: String,
+    },
+}
+
 #[cfg(feature = "circleci")]
 #[derive(Subcommand)]
 enum CircleCiCommands {
@@ -57,28 +216,120 @@ enum CircleCiCommands {
             value_hint = clap::ValueHint::FilePath
         )]
         output: PathBuf,
+        /// Print values of context variables instead of masking them
+        #[clap(long)]
+        show_secrets: bool,
+        /// Path of CircleCI config in the repositories (by default `.circleci/config.yml` or `.circleci/config.yaml`)
+        #[clap(long, value_name = "PATH")]
+        config_path: Option<String>,
+        /// Slug of the GitHub team to migrate the repositories of, instead of selecting it
+        #[clap(long, value_name = "SLUG")]
+        team: Option<String>,
     },
     /// Migrates CircleCI configuration to GitHub organization on CircleCI
     Migrate {
         /// Path to migration file
         #[clap(parse(from_os_str), value_name = "MIGRATION_FILE")]
         migration_file: PathBuf,
+        /// Print values of context variables instead of masking them
+        #[clap(long)]
+        show_secrets: bool,
+        /// Apply the migration file even if it was generated by an incompatible version
+        #[clap(long)]
+        ignore_version: bool,
     },
 }
 
 #[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", migrate_bb_to_gh::redact(&format!("{:?}", e)));
+            ExitCode::from(ErrorKind::of(&e).exit_code())
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), anyhow::Error> {
     let cmd = Cli::command();
     let version = cmd.get_version().unwrap();
     let name = cmd.get_name();
 
-    let config = config::parse_config()?;
+    // Completions don't need the configuration, so they're generated even if it cannot be parsed
+    if let Commands::Completions { shell } = &cli.command {
+        let name = name.to_string();
+        clap_complete::generate(*shell, &mut Cli::command(), name, &mut std::io::stdout());
+        return Ok(());
+    }
+
+    let mut config = config::parse_config()?;
+    if let Some(organization) = &cli.organization {
+        config.github.organization_name = organization.clone();
+    }
+    if cli.user_account {
+        config.github.user_account = true;
+    }
+    if let Some(workspace) = &cli.workspace {
+        config.bitbucket.workspace_name = workspace.clone();
+    }
+    // Validated with the overrides, which can fill in the fields left empty in the configuration file
+    config.validate()?;
+    migrate_bb_to_gh::configure_tracing(cli.trace)?;
+    migrate_bb_to_gh::configure_http_client(Duration::from_secs(cli.timeout))?;
+    migrate_bb_to_gh::configure_git_binary(&config.git.git_binary)?;
+    migrate_bb_to_gh::cleanup_on_termination()?;
+    if cli.cache || cli.refresh {
+        migrate_bb_to_gh::configure_cache(Duration::from_secs(cli.cache_ttl), cli.refresh)?;
+    }
 
     match &cli.command {
-        Commands::Wizard { output } => {
-            let wizard = Wizard::new(output.clone(), version, config.bitbucket, config.github);
+        Commands::Wizard {
+            output,
+            all_repositories,
+            user_map,
+            teams_file,
+            repos_file,
+            default_branch_name,
+            since,
+            from,
+            keep_source_default,
+            filter,
+            exclude,
+            project,
+        } => {
+            let mut wizard = Wizard::new(output.clone(), version, config.bitbucket, config.github);
+            wizard.all_repositories(*all_repositories);
+            wizard.keep_source_default(*keep_source_default);
+            if let Some(user_map) = user_map {
+                wizard.user_map(UserMap::from_file(user_map)?);
+            }
+            if let Some(teams_file) = teams_file {
+                wizard.teams_file(TeamsFile::from_file(teams_file)?);
+            }
+            if let Some(repos_file) = repos_file {
+                wizard.repos_file(repos_file);
+            }
+            if let Some(default_branch_name) = default_branch_name {
+                wizard.default_branch_name(default_branch_name);
+            }
+            if let Some(since) = since {
+                wizard.since(since);
+            }
+            if let Some(from) = from {
+                wizard.from_migration(from);
+            }
+            if let Some(filter) = filter {
+                wizard.filter(regex::Regex::new(filter)?);
+            }
+            if let Some(exclude) = exclude {
+                wizard.exclude(regex::Regex::new(exclude)?);
+            }
+            if let Some(project) = project {
+                wizard.project(project);
+            }
             let res = wizard.run().await?;
 
             println!(
@@ -92,31 +343,120 @@ async fn main() -> Result<(), anyhow::Error> {
                 output.display()
             );
         }
-        Commands::Migrate { migration_file } => {
-            let migrator = Migrator::new(migration_file, version, config);
-            let _ = migrator.migrate().await?;
+        Commands::Migrate {
+            migration_file,
+            ignore_version,
+            verify,
+            prune_refs,
+            verbose,
+            force_overwrite,
+            continue_on_error,
+            pre_hook,
+            post_hook,
+            notify_webhook,
+            report_md,
+        } => {
+            let mut migrator = Migrator::new(migration_file, version, config);
+            migrator
+                .ignore_version(*ignore_version)
+                .verify(*verify)
+                .prune_refs(*prune_refs)
+                .verbose(*verbose)
+                .force_overwrite(*force_overwrite)
+                .continue_on_error(*continue_on_error);
+            if let Some(pre_hook) = pre_hook {
+                migrator.pre_hook(pre_hook);
+            }
+            if let Some(post_hook) = post_hook {
+                migrator.post_hook(post_hook);
+            }
+            if let Some(notify_webhook) = notify_webhook {
+                migrator.notify_webhook(notify_webhook);
+            }
+            if let Some(report_md) = report_md {
+                migrator.report_md(report_md);
+            }
+            migrator.migrate().await?;
+        }
+        Commands::List { command, format } => match command {
+            ListCommands::Projects => list::list_projects(&config.bitbucket, format).await?,
+            ListCommands::Repos { project_key } => {
+                list::list_repositories(&config.bitbucket, project_key, format).await?
+            }
+        },
+        Commands::Edit { migration_file } => repositories::edit(migration_file)?,
+        Commands::Status { migration_file } => {
+            if !repositories::status(migration_file, &config).await? {
+                return Err(anyhow::anyhow!(
+                    "Some of the planned repositories or teams are missing on GitHub"
+                ));
+            }
+        }
+        Commands::Check => {
+            if !check::run(&config).await? {
+                return Err(anyhow::anyhow!("Some of the checks failed"));
+            }
         }
+        Commands::Completions { .. } => unreachable!("completions are generated before"),
         #[cfg(feature = "circleci")]
         Commands::CircleCi { command } => match &command {
-            CircleCiCommands::Wizard { output } => {
-                let res = circleci::Wizard::new(output, version, config).run().await?;
+            CircleCiCommands::Wizard {
+                output,
+                show_secrets,
+                config_path,
+                team,
+            } => {
+                let mut wizard = circleci::Wizard::new(output, version, config);
+                if let Some(config_path) = config_path {
+                    wizard.config_path(config_path);
+                }
+                if let Some(team) = team {
+                    wizard.team(team);
+                }
+                let res = wizard.run().await?;
                 println!(
                     "Migration file saved to {}",
                     std::fs::canonicalize(&res.migration_file_path)?.display()
                 );
-                println!("{}", circleci::describe_actions(&res.actions));
+                println!(
+                    "{}",
+                    circleci::describe_actions(&res.actions, *show_secrets)
+                );
                 println!(
                     "Run '{} circleci migrate {}' to start migration process",
                     name,
                     output.display()
                 );
             }
-            CircleCiCommands::Migrate { migration_file } => {
-                let migrator = circleci::Migrator::new(migration_file, version, config.circleci);
-                let _ = migrator.migrate().await?;
+            CircleCiCommands::Migrate {
+                migration_file,
+                show_secrets,
+                ignore_version,
+            } => {
+                let mut migrator =
+                    circleci::Migrator::new(migration_file, version, config.circleci);
+                migrator
+                    .show_secrets(*show_secrets)
+                    .ignore_version(*ignore_version);
+                migrator.migrate().await?;
             }
         },
     }
 
     Ok(())
 }
+
+fn validate_date(date: &str) -> Result<(), String> {
+    let regex = regex::Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap();
+    if regex.is_match(date) {
+        Ok(())
+    } else {
+        Err(format!("'{}' is not a date in YYYY-MM-DD format", date))
+    }
+}
+
+fn validate_regex(pattern: &str) -> Result<(), String> {
+    regex::Regex::new(pattern)
+        .map(|_| ())
+        .map_err(|e| format!("'{}' is not a valid regular expression: {}", pattern, e))
+}
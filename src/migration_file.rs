@@ -0,0 +1,154 @@
+use std::fs::File;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use semver::Version;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Format of the migration file, detected from the file extension
+enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => Format::Yaml,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// Action of the migration file, which can be disabled (`enabled: false`) to skip it
+/// without removing it from the file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct ActionEntry<A> {
+    #[serde(default = "enabled", skip_serializing_if = "is_enabled")]
+    pub(crate) enabled: bool,
+    #[serde(flatten)]
+    pub(crate) action: A,
+}
+
+impl<A> ActionEntry<A> {
+    pub(crate) fn new(action: A) -> Self {
+        Self {
+            enabled: true,
+            action,
+        }
+    }
+}
+
+/// Describes the numbered actions, marking the disabled ones as skipped
+pub(crate) fn describe<'a, A: 'a>(
+    actions: impl Iterator<Item = (&'a A, bool)>,
+    describe_action: impl Fn(&A) -> String,
+) -> String {
+    let mut count = 0;
+    let actions_list = actions
+        .enumerate()
+        .map(|(idx, (action, enabled))| {
+            if enabled {
+                count += 1;
+                format!("{}. {}", idx + 1, describe_action(action))
+            } else {
+                format!("{}. (skipped) {}", idx + 1, describe_action(action))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "There are {} actions to be done during migration:\n{}",
+        count, actions_list
+    )
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+pub(crate) fn read<T: DeserializeOwned>(path: &Path) -> anyhow::Result<T> {
+    let file = File::open(path)?;
+
+    let migration = match Format::from_path(path) {
+        Format::Json => serde_json::from_reader(file)?,
+        Format::Yaml => serde_yaml::from_reader(file)?,
+    };
+
+    Ok(migration)
+}
+
+pub(crate) fn write<T: Serialize>(path: &Path, migration: &T) -> anyhow::Result<()> {
+    let mut file = File::create(path)?;
+
+    match Format::from_path(path) {
+        Format::Json => serde_json::to_writer(&mut file, migration)?,
+        Format::Yaml => serde_yaml::to_writer(&mut file, migration)?,
+    }
+
+    Ok(())
+}
+
+/// Checks whether migration file generated by `file_version` can be applied by `current_version`.
+///
+/// Versions are compatible when their major and minor parts are the same.
+pub(crate) fn check_version(file_version: &str, current_version: &str) -> anyhow::Result<()> {
+    let file = Version::parse(file_version)
+        .with_context(|| format!("Invalid migration file version: {}", file_version))?;
+    let current = Version::parse(current_version)?;
+
+    if file.major != current.major || file.minor != current.minor {
+        return Err(anyhow!("Migration file version is not compatible with current version, expected: {}.{}.x, found: {}\nUse --ignore-version to apply it anyway.", current.major, current.minor, file_version));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Action {
+        CreateTeam { name: String },
+    }
+
+    #[test]
+    fn action_entries_are_enabled_by_default() {
+        let entry: ActionEntry<Action> =
+            serde_json::from_value(json!({ "create_team": { "name": "developers" } })).unwrap();
+
+        assert!(entry.enabled);
+        assert_eq!(
+            entry.action,
+            Action::CreateTeam {
+                name: "developers".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn serializes_only_disabled_flag() {
+        let mut entry = ActionEntry::new(Action::CreateTeam {
+            name: "developers".to_string(),
+        });
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            json!({ "create_team": { "name": "developers" } })
+        );
+
+        entry.enabled = false;
+        assert_eq!(
+            serde_json::to_value(&entry).unwrap(),
+            json!({ "enabled": false, "create_team": { "name": "developers" } })
+        );
+    }
+}
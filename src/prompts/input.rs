@@ -25,10 +25,21 @@ type InputValidator = Box<dyn Fn(&str) -> Option<String>>;
 ///     .interact()
 ///     .expect("could not interact");
 /// ```
+/// ## Hidden input
+/// ```rust,no_run
+/// use migrate_bb_to_gh::prompts::Input;
+///
+/// let token: String = Input::with_prompt("API token")
+///     .password(true)
+///     .interact()
+///     .expect("could not interact");
+/// ```
 pub struct Input {
     prompt: String,
     initial_text: String,
     validator: Option<InputValidator>,
+    password: bool,
+    allow_empty: bool,
 }
 
 impl Input {
@@ -37,6 +48,8 @@ impl Input {
             prompt: prompt.into(),
             initial_text: "".into(),
             validator: None,
+            password: false,
+            allow_empty: false,
         }
     }
 
@@ -45,6 +58,20 @@ impl Input {
         self
     }
 
+    /// Hides the typed characters, intended for secrets.
+    ///
+    /// Initial text is ignored for hidden inputs.
+    pub fn password(&mut self, password: bool) -> &mut Self {
+        self.password = password;
+        self
+    }
+
+    /// Accepts an empty input instead of waiting for a non-empty one.
+    pub fn allow_empty(&mut self, allow_empty: bool) -> &mut Self {
+        self.allow_empty = allow_empty;
+        self
+    }
+
     pub fn validate_with<F>(&mut self, validator: F) -> &mut Self
     where
         F: 'static + Fn(&str) -> Option<String>,
@@ -54,14 +81,22 @@ impl Input {
     }
 
     pub fn interact(&self) -> io::Result<String> {
-        use dialoguer::Input;
+        use dialoguer::{Input, Password};
 
         let theme = default_theme();
         loop {
-            let input: String = Input::with_theme(&theme)
-                .with_prompt(&self.prompt)
-                .with_initial_text(&self.initial_text)
-                .interact()?;
+            let input: String = if self.password {
+                Password::with_theme(&theme)
+                    .with_prompt(&self.prompt)
+                    .allow_empty_password(self.allow_empty)
+                    .interact()?
+            } else {
+                Input::with_theme(&theme)
+                    .with_prompt(&self.prompt)
+                    .with_initial_text(&self.initial_text)
+                    .allow_empty(self.allow_empty)
+                    .interact()?
+            };
 
             if let Some(validator) = &self.validator {
                 let err = validator(&input);
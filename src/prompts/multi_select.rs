@@ -4,6 +4,7 @@ use std::io;
 
 pub struct MultiSelect<'a, T> {
     items: Vec<&'a T>,
+    defaults: Vec<bool>,
     prompt: String,
 }
 
@@ -14,6 +15,7 @@ where
     pub fn with_prompt<S: Into<String>>(prompt: S) -> Self {
         Self {
             items: vec![],
+            defaults: vec![],
             prompt: prompt.into(),
         }
     }
@@ -25,6 +27,12 @@ where
         self
     }
 
+    /// Sets which of the items are selected initially, in the same order as the items.
+    pub fn defaults(&mut self, defaults: &[bool]) -> &mut Self {
+        self.defaults = defaults.to_vec();
+        self
+    }
+
     pub fn interact(&self) -> io::Result<Vec<&'a T>> {
         let indices = self.interact_idx()?;
 
@@ -39,17 +47,65 @@ where
     pub fn interact_idx(&self) -> io::Result<Vec<usize>> {
         use dialoguer::MultiSelect;
 
-        MultiSelect::with_theme(&default_theme())
-            .with_prompt(format!(
-                "{prompt}\n{tip}",
-                prompt = &self.prompt,
-                tip = prompt_tip()
-            ))
-            .items(&self.items)
-            .interact()
+        // With more than one item, synthetic "[ALL]" and "[NONE]" items are shown at the top.
+        // Confirming the selection with one of them checked shows the prompt again,
+        // with all (or none) of the items checked.
+        let shortcuts = if self.items.len() > 1 {
+            vec![SELECT_ALL, SELECT_NONE]
+        } else {
+            vec![]
+        };
+        let labels = shortcuts
+            .iter()
+            .map(|s| s.to_string())
+            .chain(self.items.iter().map(|i| i.to_string()))
+            .collect::<Vec<_>>();
+
+        let mut checked = (0..self.items.len())
+            .map(|idx| self.defaults.get(idx).copied().unwrap_or(false))
+            .collect::<Vec<_>>();
+
+        loop {
+            let defaults = shortcuts
+                .iter()
+                .map(|_| false)
+                .chain(checked.iter().copied())
+                .collect::<Vec<_>>();
+
+            let selected = MultiSelect::with_theme(&default_theme())
+                .with_prompt(format!(
+                    "{prompt}\n{tip}",
+                    prompt = &self.prompt,
+                    tip = prompt_tip(!shortcuts.is_empty())
+                ))
+                .items(&labels)
+                .defaults(&defaults)
+                .interact()?;
+
+            let selected_shortcut = selected
+                .iter()
+                .find(|&&idx| idx < shortcuts.len())
+                .map(|&idx| shortcuts[idx]);
+            match selected_shortcut {
+                Some(shortcut) => checked.fill(shortcut == SELECT_ALL),
+                None => {
+                    return Ok(selected
+                        .into_iter()
+                        .map(|idx| idx - shortcuts.len())
+                        .collect())
+                }
+            }
+        }
     }
 }
 
-fn prompt_tip() -> &'static str {
-    "[Space = select, Enter = continue]"
+const SELECT_ALL: &str = "[ALL]";
+const SELECT_NONE: &str = "[NONE]";
+
+fn prompt_tip(with_shortcuts: bool) -> &'static str {
+    if with_shortcuts {
+        "[Space = select, Enter = continue, check [ALL] or [NONE] and press Enter to select all or none]"
+    } else {
+        "[Space = select, Enter = continue]"
+    }
 }
@@ -33,8 +33,12 @@ where
     }
 
     pub fn interact(&self) -> io::Result<&'a T> {
+        let idx = self.interact_idx()?;
+
         let selected = self
-            .interact_opt()?
+            .items
+            .get(idx)
+            .copied()
             .expect("At least 1 item must be selected");
 
         Ok(selected)
@@ -54,15 +58,35 @@ where
             .interact()
     }
 
+    /// Returns `None` when the user cancels the selection with Esc or q
     pub fn interact_opt(&self) -> io::Result<Option<&'a T>> {
-        let idx = self.interact_idx()?;
-
-        let selected: Option<&'a T> = self.items.get(idx).copied();
+        let selected = self
+            .interact_idx_opt()?
+            .and_then(|idx| self.items.get(idx).copied());
 
         Ok(selected)
     }
+
+    /// Returns `None` when the user cancels the selection with Esc or q
+    pub fn interact_idx_opt(&self) -> io::Result<Option<usize>> {
+        use dialoguer::Select;
+
+        Select::with_theme(&default_theme())
+            .with_prompt(format!(
+                "{prompt}\n{tip}",
+                prompt = &self.prompt,
+                tip = cancelable_prompt_tip()
+            ))
+            .items(&self.items)
+            .default(self.default)
+            .interact_opt()
+    }
 }
 
 fn prompt_tip() -> &'static str {
     "[Space OR Enter = continue]"
 }
+
+fn cancelable_prompt_tip() -> &'static str {
+    "[Space OR Enter = continue, Esc = cancel]"
+}
@@ -1,12 +1,61 @@
 use crate::bitbucket;
-use crate::github::TeamRepositoryPermission;
+use crate::github::{
+    RepositoryInit, RepositoryMetadata, RepositorySettings, RepositoryVisibility,
+    TeamRepositoryPermission,
+};
+use crate::migration_file::{self, ActionEntry};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Repository {
     pub(crate) clone_link: String,
     name: String,
     pub(crate) full_name: String,
+    /// Branches to push (with all tags), instead of mirroring all the refs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) branches: Option<Vec<String>>,
+    /// Visibility of the repository created in GitHub, private when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) visibility: Option<RepositoryVisibility>,
+    /// Name of the repository in GitHub, when it should differ from the one in Bitbucket
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target_name: Option<String>,
+    /// Description and homepage from Bitbucket
+    #[serde(flatten)]
+    pub(crate) metadata: RepositoryMetadata,
+}
+
+/// Name of the GitHub repository migrated from the Bitbucket repository `full_name`,
+/// i.e. the name without the `{workspace}/` part (or any other owner part).
+pub(crate) fn github_repository_name<'a>(full_name: &'a str, workspace: &str) -> &'a str {
+    full_name
+        .strip_prefix(workspace)
+        .and_then(|name| name.strip_prefix('/'))
+        .unwrap_or_else(|| full_name.rsplit('/').next().unwrap_or(full_name))
+}
+
+/// Owner-qualified name of the GitHub repository migrated from the Bitbucket repository `full_name`,
+/// e.g. `org/api` for `acme/api` migrated from `acme` workspace to `org` organization.
+pub(crate) fn github_full_name(full_name: &str, workspace: &str, organization: &str) -> String {
+    format!(
+        "{}/{}",
+        organization,
+        github_repository_name(full_name, workspace)
+    )
+}
+
+impl Repository {
+    /// Name of the repository created in GitHub
+    pub(crate) fn github_name(&self, workspace: &str) -> String {
+        match &self.target_name {
+            Some(target_name) => target_name.clone(),
+            None => github_repository_name(&self.full_name, workspace).to_string(),
+        }
+    }
+
+    pub(crate) fn visibility(&self) -> RepositoryVisibility {
+        self.visibility.clone().unwrap_or_default()
+    }
 }
 
 impl From<bitbucket::Repository> for Repository {
@@ -17,19 +66,51 @@ impl From<bitbucket::Repository> for Repository {
                 .get_ssh_url()
                 .unwrap_or_else(|| panic!("missing SSH clone url for {}", repository.full_name)),
             full_name: repository.full_name,
+            branches: None,
+            visibility: None,
+            target_name: None,
+            metadata: RepositoryMetadata {
+                description: repository.description.filter(|d| !d.is_empty()),
+                homepage: repository.website.filter(|w| !w.is_empty()),
+            },
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Collaborator {
+    pub(crate) login: String,
+    pub(crate) permission: TeamRepositoryPermission,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DeployKey {
+    pub(crate) title: String,
+    /// Public part of the key
+    pub(crate) key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum Action {
     MigrateRepositories {
         repositories: Vec<Repository>,
     },
+    /// Creates empty repositories, without mirroring their contents from Bitbucket
+    CreateRepositories {
+        repositories: Vec<Repository>,
+        /// Initial commit of the repositories, created without any when not set
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        init: Option<RepositoryInit>,
+    },
     CreateTeam {
         name: String,
         repositories: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// Slug of the existing team the created one is nested under
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parent_team_slug: Option<String>,
     },
     AddMembersToTeam {
         team_name: String,
@@ -45,6 +126,37 @@ pub enum Action {
     SetRepositoryDefaultBranch {
         repository_name: String,
         branch: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rename_to: Option<String>,
+    },
+    AddRepositoriesToExistingTeam {
+        team_name: String,
+        team_slug: String,
+        permission: TeamRepositoryPermission,
+        repositories: Vec<String>,
+    },
+    CreateCodeowners {
+        repository_name: String,
+        team_slugs: Vec<String>,
+    },
+    AddCollaborators {
+        repository_name: String,
+        collaborators: Vec<Collaborator>,
+    },
+    MigrateDeployKeys {
+        repository_name: String,
+        keys: Vec<DeployKey>,
+    },
+    SetRepositorySettings {
+        repository_name: String,
+        settings: RepositorySettings,
+    },
+    /// Renames the repository in GitHub, done after all other actions using the repository
+    RenameRepository {
+        /// Full name of the repository
+        from_name: String,
+        /// New name of the repository, without the organization
+        to_name: String,
     },
 }
 
@@ -54,7 +166,23 @@ impl Action {
             Action::MigrateRepositories { repositories } => {
                 let repositories_list = repositories
                     .iter()
-                    .map(|r| format!("  - {}", r.full_name))
+                    .map(|r| {
+                        let mut details = vec![];
+                        if let Some(target_name) = &r.target_name {
+                            details.push(format!("as {}", target_name));
+                        }
+                        if let Some(visibility) = &r.visibility {
+                            details.push(visibility.to_string());
+                        }
+                        if let Some(branches) = &r.branches {
+                            details.push(format!("branches: {}", branches.join(", ")));
+                        }
+                        if details.is_empty() {
+                            format!("  - {}", r.full_name)
+                        } else {
+                            format!("  - {} ({})", r.full_name, details.join(", "))
+                        }
+                    })
                     .collect::<Vec<_>>()
                     .join("\n");
                 format!(
@@ -63,15 +191,56 @@ impl Action {
                     repositories_list
                 )
             }
-            Action::CreateTeam { name, repositories } => {
+            Action::CreateRepositories { repositories, init } => {
+                let repositories_list = repositories
+                    .iter()
+                    .map(|r| match (&r.target_name, &r.visibility) {
+                        (Some(target_name), Some(visibility)) => {
+                            format!("  - {} (as {}, {})", r.full_name, target_name, visibility)
+                        }
+                        (Some(target_name), None) => {
+                            format!("  - {} (as {})", r.full_name, target_name)
+                        }
+                        (None, Some(visibility)) => {
+                            format!("  - {} ({})", r.full_name, visibility)
+                        }
+                        (None, None) => format!("  - {}", r.full_name),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                match init {
+                    Some(init) => format!(
+                        "Create {} repositories initialized with {} (without mirroring):\n{}",
+                        repositories.len(),
+                        init,
+                        repositories_list
+                    ),
+                    None => format!(
+                        "Create {} empty repositories (without mirroring):\n{}",
+                        repositories.len(),
+                        repositories_list
+                    ),
+                }
+            }
+            Action::CreateTeam {
+                name,
+                repositories,
+                parent_team_slug,
+                ..
+            } => {
                 let repositories_list = repositories
                     .iter()
                     .map(|r| format!("  - {}", r))
                     .collect::<Vec<_>>()
                     .join("\n");
+                let parent = parent_team_slug
+                    .as_ref()
+                    .map(|slug| format!(" (nested under '{}' team)", slug))
+                    .unwrap_or_default();
                 format!(
-                    "Create team named '{}' with access to {} repositories:\n{}",
+                    "Create team named '{}'{} with access to {} repositories:\n{}",
                     name,
+                    parent,
                     repositories.len(),
                     repositories_list
                 )
@@ -114,26 +283,205 @@ impl Action {
             Action::SetRepositoryDefaultBranch {
                 repository_name,
                 branch,
+                rename_to: None,
             } => {
                 format!(
                     "Set default branch of '{}' repository to '{}'",
                     repository_name, branch
                 )
             }
+            Action::SetRepositoryDefaultBranch {
+                repository_name,
+                branch,
+                rename_to: Some(new_name),
+            } => {
+                format!(
+                    "Rename '{}' branch of '{}' repository to '{}' and set it as default",
+                    branch, repository_name, new_name
+                )
+            }
+            Action::AddRepositoriesToExistingTeam {
+                team_name,
+                permission,
+                repositories,
+                ..
+            } => {
+                let repositories_list = repositories
+                    .iter()
+                    .map(|r| format!("  - {}", r))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    "Give existing team {} access to {} repositories ({}):\n{}",
+                    team_name,
+                    repositories.len(),
+                    permission,
+                    repositories_list
+                )
+            }
+            Action::CreateCodeowners {
+                repository_name,
+                team_slugs,
+            } => {
+                format!(
+                    "Create CODEOWNERS file in '{}' repository owned by teams: {}",
+                    repository_name,
+                    team_slugs.join(", ")
+                )
+            }
+            Action::AddCollaborators {
+                repository_name,
+                collaborators,
+            } => {
+                let collaborators_list = collaborators
+                    .iter()
+                    .map(|c| format!("  - {} ({})", c.login, c.permission))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    "Add {} collaborators to '{}' repository:\n{}",
+                    collaborators.len(),
+                    repository_name,
+                    collaborators_list
+                )
+            }
+            Action::MigrateDeployKeys {
+                repository_name,
+                keys,
+            } => {
+                let keys_list = keys
+                    .iter()
+                    .map(|k| format!("  - {}", k.title))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!(
+                    "Add {} deploy keys (read-only) to '{}' repository:\n{}",
+                    keys.len(),
+                    repository_name,
+                    keys_list
+                )
+            }
+            Action::SetRepositorySettings {
+                repository_name,
+                settings,
+            } => {
+                format!(
+                    "Change settings of '{}' repository ({})",
+                    repository_name, settings
+                )
+            }
+            Action::RenameRepository { from_name, to_name } => {
+                format!("Rename '{}' repository to '{}'", from_name, to_name)
+            }
+        }
+    }
+}
+
+/// Removes duplicated actions, merging repositories of the actions assigning them
+/// to the same team with the same permission.
+pub(crate) fn dedupe_actions(actions: Vec<Action>) -> Vec<Action> {
+    let mut deduped: Vec<Action> = Vec::with_capacity(actions.len());
+    for action in actions {
+        if deduped.contains(&action) {
+            continue;
+        }
+        let merged_into = deduped
+            .iter_mut()
+            .find_map(|existing| match (existing, &action) {
+                (
+                    Action::AssignRepositoriesToTeam {
+                        team_slug,
+                        permission,
+                        repositories,
+                        ..
+                    },
+                    Action::AssignRepositoriesToTeam {
+                        team_slug: other_slug,
+                        permission: other_permission,
+                        repositories: other_repositories,
+                        ..
+                    },
+                )
+                | (
+                    Action::AddRepositoriesToExistingTeam {
+                        team_slug,
+                        permission,
+                        repositories,
+                        ..
+                    },
+                    Action::AddRepositoriesToExistingTeam {
+                        team_slug: other_slug,
+                        permission: other_permission,
+                        repositories: other_repositories,
+                        ..
+                    },
+                ) if team_slug == other_slug && permission == other_permission => {
+                    Some((repositories, other_repositories))
+                }
+                _ => None,
+            });
+
+        match merged_into {
+            Some((repositories, other_repositories)) => {
+                for repository in other_repositories {
+                    if !repositories.contains(repository) {
+                        repositories.push(repository.clone());
+                    }
+                }
+            }
+            None => deduped.push(action),
         }
     }
+    deduped
 }
 
 pub fn describe_actions(actions: &[Action]) -> String {
-    let actions_list = actions
-        .iter()
-        .enumerate()
-        .map(|(idx, action)| format!("{}. {}", idx + 1, action.describe()))
-        .collect::<Vec<_>>()
-        .join("\n");
-    format!(
-        "There are {} actions to be done during migration:\n{}",
-        actions.len(),
-        actions_list
+    migration_file::describe(
+        actions.iter().map(|action| (action, true)),
+        Action::describe,
     )
 }
+
+/// Describes actions of the migration file, marking the disabled ones as skipped
+pub(crate) fn describe_action_entries(entries: &[ActionEntry<Action>]) -> String {
+    migration_file::describe(
+        entries.iter().map(|entry| (&entry.action, entry.enabled)),
+        Action::describe,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repository(full_name: &str) -> Repository {
+        Repository {
+            clone_link: format!("git@bitbucket.org:{}.git", full_name),
+            name: full_name.rsplit('/').next().unwrap().to_string(),
+            full_name: full_name.to_string(),
+            branches: None,
+            visibility: None,
+            target_name: None,
+            metadata: RepositoryMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn github_names_drop_bitbucket_workspace() {
+        let repository = repository("acme/api");
+
+        assert_eq!(repository.github_name("acme"), "api");
+        assert_eq!(
+            github_full_name(&repository.full_name, "acme", "org"),
+            "org/api"
+        );
+    }
+
+    #[test]
+    fn github_name_prefers_target_name() {
+        let mut repository = repository("acme/api");
+        repository.target_name = Some("api-server".to_string());
+
+        assert_eq!(repository.github_name("acme"), "api-server");
+    }
+}
@@ -0,0 +1,196 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+use crate::migration_file::{self, ActionEntry};
+use crate::prompts::{Confirm, MultiSelect, Select};
+use crate::repositories::action::{describe_action_entries, Action};
+use crate::repositories::migrator::Migration;
+
+/// Team or repository created by one action and used by another
+#[derive(PartialEq, Debug)]
+enum Dependency {
+    Team(String),
+    Repository(String),
+}
+
+impl Dependency {
+    /// Repositories are referenced with or without the workspace/organization part
+    fn repository(name: &str) -> Self {
+        Dependency::Repository(name.rsplit('/').next().unwrap_or(name).to_lowercase())
+    }
+}
+
+/// Interactively drops and reorders actions of the migration file, saving the result in the same file.
+pub fn edit(migration_file: &Path) -> anyhow::Result<()> {
+    let mut migration: Migration = migration_file::read(migration_file)
+        .with_context(|| format!("Error when parsing {} file", migration_file.display()))?;
+
+    let all_actions = migration.actions();
+    let labels = entry_labels(all_actions);
+    let selected = MultiSelect::with_prompt("Select actions to keep")
+        .items(&labels)
+        .defaults(&vec![true; labels.len()])
+        .interact_idx()?;
+    let mut actions = selected
+        .into_iter()
+        .map(|idx| all_actions[idx].clone())
+        .collect::<Vec<_>>();
+
+    if actions.len() > 1
+        && Confirm::with_prompt("Do you want to change the order of the actions?").interact()?
+    {
+        reorder(&mut actions)?;
+    }
+
+    let enabled = actions
+        .iter()
+        .filter(|entry| entry.enabled)
+        .map(|entry| entry.action.clone())
+        .collect::<Vec<_>>();
+    let warnings = dependency_warnings(&enabled);
+    if !warnings.is_empty() {
+        eprintln!("Warning: some actions depend on the ones done after them:");
+        for warning in &warnings {
+            eprintln!("  - {}", warning);
+        }
+        if !Confirm::with_prompt("Do you want to save the migration file anyway?").interact()? {
+            return Err(anyhow!("Editing canceled"));
+        }
+    }
+
+    println!("{}", describe_action_entries(&actions));
+    migration.set_actions(actions);
+    migration_file::write(migration_file, &migration)?;
+    println!("Migration file saved to {}", migration_file.display());
+
+    Ok(())
+}
+
+/// First lines of the actions descriptions, as the lists of repositories don't fit the prompts
+fn labels(actions: &[Action]) -> Vec<String> {
+    actions
+        .iter()
+        .map(|action| {
+            let description = action.describe();
+            description.lines().next().unwrap_or_default().to_string()
+        })
+        .collect()
+}
+
+/// Labels of the actions, marking the ones disabled in the migration file
+fn entry_labels(entries: &[ActionEntry<Action>]) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| {
+            let label = labels(std::slice::from_ref(&entry.action)).remove(0);
+            if entry.enabled {
+                label
+            } else {
+                format!("(skipped) {}", label)
+            }
+        })
+        .collect()
+}
+
+fn reorder(actions: &mut Vec<ActionEntry<Action>>) -> anyhow::Result<()> {
+    loop {
+        let labels = entry_labels(actions)
+            .into_iter()
+            .enumerate()
+            .map(|(idx, label)| format!("{}. {}", idx + 1, label))
+            .collect::<Vec<_>>();
+        let from = match Select::with_prompt("Select action to move (Esc to finish reordering)")
+            .items(&labels)
+            .interact_idx_opt()?
+        {
+            Some(idx) => idx,
+            None => return Ok(()),
+        };
+        let to = Select::with_prompt(format!("Select new position of '{}'", labels[from]))
+            .items(&labels)
+            .default(from)
+            .interact_idx()?;
+
+        let action = actions.remove(from);
+        actions.insert(to, action);
+    }
+}
+
+fn provided(action: &Action) -> Vec<Dependency> {
+    match action {
+        Action::CreateTeam { name, .. } => vec![Dependency::Team(name.to_lowercase())],
+        Action::RenameRepository { to_name, .. } => vec![Dependency::repository(to_name)],
+        Action::MigrateRepositories { repositories }
+        | Action::CreateRepositories { repositories, .. } => repositories
+            .iter()
+            .map(|r| Dependency::repository(&r.full_name))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn required(action: &Action) -> Vec<Dependency> {
+    match action {
+        Action::AssignRepositoriesToTeam {
+            team_name,
+            repositories,
+            ..
+        } => std::iter::once(Dependency::Team(team_name.to_lowercase()))
+            .chain(repositories.iter().map(|r| Dependency::repository(r)))
+            .collect(),
+        Action::AddMembersToTeam { team_name, .. } => {
+            vec![Dependency::Team(team_name.to_lowercase())]
+        }
+        Action::AddRepositoriesToExistingTeam { repositories, .. } => repositories
+            .iter()
+            .map(|r| Dependency::repository(r))
+            .collect(),
+        Action::SetRepositoryDefaultBranch {
+            repository_name, ..
+        }
+        | Action::CreateCodeowners {
+            repository_name, ..
+        }
+        | Action::AddCollaborators {
+            repository_name, ..
+        }
+        | Action::MigrateDeployKeys {
+            repository_name, ..
+        }
+        | Action::SetRepositorySettings {
+            repository_name, ..
+        }
+        | Action::RenameRepository {
+            from_name: repository_name,
+            ..
+        } => vec![Dependency::repository(repository_name)],
+        Action::CreateTeam { .. }
+        | Action::MigrateRepositories { .. }
+        | Action::CreateRepositories { .. } => vec![],
+    }
+}
+
+/// Describes actions which use teams or repositories created by the actions done after them
+fn dependency_warnings(actions: &[Action]) -> Vec<String> {
+    let labels = labels(actions);
+    let mut warnings = vec![];
+    for (idx, action) in actions.iter().enumerate() {
+        let required = required(action);
+        let later = actions
+            .iter()
+            .enumerate()
+            .skip(idx + 1)
+            .find(|(_, later)| provided(later).iter().any(|d| required.contains(d)));
+        if let Some((later_idx, _)) = later {
+            warnings.push(format!(
+                "{}. '{}' should be done after {}. '{}'",
+                idx + 1,
+                labels[idx],
+                later_idx + 1,
+                labels[later_idx]
+            ));
+        }
+    }
+    warnings
+}
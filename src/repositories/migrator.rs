@@ -1,41 +1,201 @@
-use std::io::Write;
-use std::os::unix::fs::PermissionsExt;
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::path::PathBuf;
-use std::{fs, fs::File, path::Path, process::Command, time::Instant};
+use std::{
+    path::Path,
+    process::Command,
+    time::{Duration, Instant},
+};
 
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 use tempdir::TempDir;
 
-use crate::{github::TeamRepositoryPermission, spinner};
+use crate::error::PartialMigrationError;
+use crate::redact::redact;
+use crate::{git, github::TeamRepositoryPermission, migration_file, spinner};
 
 use crate::config::{Config, GitConfig};
-use crate::github::GithubApi;
+use crate::github::{GithubApi, RepositoryInit};
+use crate::migration_file::ActionEntry;
 use crate::prompts::Confirm;
-use crate::repositories::action::{describe_actions, Action, Repository};
+use crate::repositories::action::{
+    describe_action_entries, github_full_name, github_repository_name, Action, Collaborator,
+    DeployKey, Repository,
+};
+use crate::repositories::summary::{
+    CreatedTeam, DefaultBranchChange, Failure, MigratedRepository, Summary,
+};
 use anyhow::{anyhow, Context};
+use futures::StreamExt;
 use tokio::task::JoinHandle;
+use tracing::Instrument;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Migration {
     version: String,
-    actions: Vec<Action>,
+    actions: Vec<ActionEntry<Action>>,
+    /// Repositories chosen in the wizard, used to bootstrap the next wizard run with `--from`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selection: Option<Selection>,
+    /// Repositories left out in the wizard, listed in the summary of the migration
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    skipped: Vec<SkippedRepository>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct Selection {
+    /// Name of the Bitbucket project, empty when the repositories were listed in a file
+    pub(crate) project: String,
+    /// Full names of the Bitbucket repositories
+    pub(crate) repositories: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct SkippedRepository {
+    /// Full name of the Bitbucket repository
+    pub(crate) name: String,
+    pub(crate) reason: String,
 }
 
 impl Migration {
     pub fn new(version: &str, actions: &[Action]) -> Self {
         Self {
             version: version.to_string(),
-            actions: actions.to_vec(),
+            actions: actions.iter().cloned().map(ActionEntry::new).collect(),
+            selection: None,
+            skipped: vec![],
         }
     }
+
+    pub(crate) fn actions(&self) -> &[ActionEntry<Action>] {
+        &self.actions
+    }
+
+    /// Actions which are not disabled in the migration file
+    pub(crate) fn enabled_actions(&self) -> impl Iterator<Item = &Action> {
+        self.actions
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| &entry.action)
+    }
+
+    pub(crate) fn set_actions(&mut self, actions: Vec<ActionEntry<Action>>) {
+        self.actions = actions;
+    }
+
+    pub(crate) fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+
+    pub(crate) fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+
+    pub(crate) fn set_skipped(&mut self, skipped: Vec<SkippedRepository>) {
+        self.skipped = skipped;
+    }
+}
+
+/// Step of the repository migration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MigrationStep {
+    Clone,
+    Create,
+    Push,
+    Verify,
+    Cleanup,
+}
+
+impl Display for MigrationStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrationStep::Clone => write!(f, "cloning from Bitbucket"),
+            MigrationStep::Create => write!(f, "creating GitHub repository"),
+            MigrationStep::Push => write!(f, "pushing to GitHub"),
+            MigrationStep::Verify => write!(f, "verifying the mirror"),
+            MigrationStep::Cleanup => write!(f, "deleting the local copy"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum MigrationStatus {
+    /// Repository was migrated, with refs mismatched between the local copy and GitHub (when verified)
+    Migrated { discrepancies: Vec<String> },
+    Failed {
+        step: MigrationStep,
+        error: anyhow::Error,
+    },
+}
+
+/// Result of migrating a single repository, for reporting
+#[derive(Debug)]
+pub(crate) struct MigrationOutcome {
+    pub(crate) repo: Repository,
+    /// URL of the repository in GitHub, known once it has been created
+    pub(crate) github_url: Option<String>,
+    pub(crate) duration: Duration,
+    pub(crate) status: MigrationStatus,
+}
+
+#[derive(Clone, Copy)]
+struct MirrorOptions {
+    verify: bool,
+    prune_refs: bool,
+    verbose: bool,
+    force_overwrite: bool,
+}
+
+impl MirrorOptions {
+    /// Steps of a single repository migration shown in its progress bar
+    fn steps_count(&self) -> u64 {
+        if self.verify {
+            5
+        } else {
+            4
+        }
+    }
+}
+
+/// Progress bars of the repositories migrated at the same time, with the total across all of them
+struct MigrationProgress {
+    bars: MultiProgress,
+    total: ProgressBar,
+}
+
+impl MigrationProgress {
+    fn new(repositories: usize, steps_count: u64) -> Self {
+        let bars = MultiProgress::new();
+        let total = bars.add(ProgressBar::new(repositories as u64 * steps_count));
+        total.set_style(progress_bar_style());
+        total.set_message(format!("Migrating {} repositories", repositories));
+        Self { bars, total }
+    }
 }
 
 pub struct Migrator {
     migration_file: PathBuf,
     version: String,
     github: GithubApi,
+    /// Bitbucket workspace of the migrated repositories, dropped from their names in GitHub
+    workspace_name: String,
     git_config: GitConfig,
+    ignore_version: bool,
+    verify: bool,
+    prune_refs: bool,
+    verbose: bool,
+    force_overwrite: bool,
+    continue_on_error: bool,
+    pre_hook: Option<String>,
+    post_hook: Option<String>,
+    notify_webhook: Option<String>,
+    report_md: Option<PathBuf>,
+    summary: Summary,
+    /// Slugs of the teams created during the migration (by team name), as returned by GitHub
+    created_teams: HashMap<String, String>,
+    /// Names of the repositories created with `target_name`, by their (lowercase) names planned by the wizard
+    target_names: HashMap<String, String>,
 }
 
 impl Migrator {
@@ -44,28 +204,196 @@ impl Migrator {
             migration_file: migration_file.to_path_buf(),
             version: version.to_string(),
             github: GithubApi::new(&config.github),
+            workspace_name: config.bitbucket.workspace_name,
             git_config: config.git,
+            ignore_version: false,
+            verify: false,
+            prune_refs: false,
+            verbose: false,
+            force_overwrite: false,
+            continue_on_error: false,
+            pre_hook: None,
+            post_hook: None,
+            notify_webhook: None,
+            report_md: None,
+            summary: Summary::default(),
+            created_teams: HashMap::new(),
+            target_names: HashMap::new(),
+        }
+    }
+
+    /// Applies the migration file even if it was generated by an incompatible version.
+    pub fn ignore_version(&mut self, ignore_version: bool) -> &mut Self {
+        self.ignore_version = ignore_version;
+        self
+    }
+
+    /// Compares refs of each pushed mirror with the refs on GitHub, reporting any discrepancies.
+    pub fn verify(&mut self, verify: bool) -> &mut Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Deletes refs other than branches and tags from the mirrors before pushing them.
+    pub fn prune_refs(&mut self, prune_refs: bool) -> &mut Self {
+        self.prune_refs = prune_refs;
+        self
+    }
+
+    /// Shows the progress reported by `git` commands while cloning and pushing the repositories.
+    pub fn verbose(&mut self, verbose: bool) -> &mut Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Pushes the mirrors even if it rewrites or deletes refs already existing in GitHub.
+    pub fn force_overwrite(&mut self, force_overwrite: bool) -> &mut Self {
+        self.force_overwrite = force_overwrite;
+        self
+    }
+
+    /// Continues with the next actions when some of the repositories failed to migrate.
+    pub fn continue_on_error(&mut self, continue_on_error: bool) -> &mut Self {
+        self.continue_on_error = continue_on_error;
+        self
+    }
+
+    /// Shell command executed before the first action of the migration.
+    pub fn pre_hook(&mut self, command: &str) -> &mut Self {
+        self.pre_hook = Some(command.to_string());
+        self
+    }
+
+    /// Shell command executed after the last action of the migration (also when it failed).
+    pub fn post_hook(&mut self, command: &str) -> &mut Self {
+        self.post_hook = Some(command.to_string());
+        self
+    }
+
+    /// URL to which a JSON summary is POSTed when the migration ends (successfully or not).
+    pub fn notify_webhook(&mut self, url: &str) -> &mut Self {
+        self.notify_webhook = Some(url.to_string());
+        self
+    }
+
+    /// Path to a Markdown file where the plan and the outcome of the migration are written.
+    pub fn report_md(&mut self, path: &Path) -> &mut Self {
+        self.report_md = Some(path.to_path_buf());
+        self
+    }
+
+    /// Runs the hook command with `sh`, exposing the path to the migration file
+    /// in `MIGRATION_FILE` environment variable.
+    fn run_hook(&self, name: &str, command: &str, envs: &[(&str, &str)]) -> anyhow::Result<()> {
+        println!("Running {} hook: {}", name, command);
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("MIGRATION_FILE", &self.migration_file)
+            .envs(envs.iter().copied())
+            .status()
+            .with_context(|| format!("Failed to run {} hook", name))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!("{} hook failed with {}", name, status))
         }
     }
 
     async fn add_members_to_team(
-        &self,
+        &mut self,
         team_name: &str,
         team_slug: &str,
         members: &[String],
     ) -> anyhow::Result<()> {
+        const CONCURRENT_REQUESTS: usize = 8;
+
         println!("Adding {} members to {} team", members.len(), team_name,);
         let pb = ProgressBar::new(members.len() as u64);
         pb.set_style(progress_bar_style());
-        for member in members {
-            self.github
-                .update_team_membership(team_slug, member)
-                .await?;
-            pb.inc(1);
+        let github = &self.github;
+        let failures = futures::stream::iter(members)
+            .map(|member| {
+                let pb = &pb;
+                async move {
+                    let res = github.update_team_membership(team_slug, member).await;
+                    pb.inc(1);
+                    res.err().map(|e| (member, e))
+                }
+            })
+            .buffer_unordered(CONCURRENT_REQUESTS)
+            .filter_map(|failure| async { failure })
+            .collect::<Vec<_>>()
+            .await;
+        pb.finish_with_message(format!(
+            "Added {} members, {} failed",
+            members.len() - failures.len(),
+            failures.len()
+        ));
+
+        for (member, e) in &failures {
+            eprintln!("Failed to add {} to {} team: {}", member, team_name, e);
         }
+
+        if let Some(team) = self
+            .summary
+            .created_teams
+            .iter_mut()
+            .find(|team| team.name == team_name)
+        {
+            team.members.extend(
+                members
+                    .iter()
+                    .filter(|member| !failures.iter().any(|(failed, _)| failed == member))
+                    .cloned(),
+            );
+        }
+        self.summary
+            .failures
+            .extend(failures.into_iter().map(|(member, e)| Failure {
+                repository: None,
+                error: redact(&format!(
+                    "Failed to add {} to {} team: {:#}",
+                    member, team_name, e
+                )),
+            }));
         Ok(())
     }
 
+    /// Renames the branch, unless a branch with the new name already exists.
+    ///
+    /// Returns name of the branch which should become the default one.
+    async fn rename_branch(
+        &self,
+        repo_name: &str,
+        branch: &str,
+        new_name: &str,
+    ) -> anyhow::Result<String> {
+        let branches = self.github.get_repo_branches(repo_name).await?;
+        if branches.iter().any(|b| b.name == new_name) {
+            println!(
+                "Branch '{}' already exists in '{}' repository, skipping renaming of '{}' branch",
+                new_name, repo_name, branch
+            );
+            return Ok(branch.to_string());
+        }
+
+        let spinner = spinner::create_spinner(format!(
+            "Renaming '{}' branch of '{}' repository to '{}'",
+            branch, repo_name, new_name
+        ));
+        let renamed = self
+            .github
+            .rename_branch(repo_name, branch, new_name)
+            .await?;
+        spinner.finish_with_message(format!(
+            "Renamed '{}' branch of '{}' repository to '{}'",
+            branch, repo_name, renamed.name
+        ));
+        Ok(renamed.name)
+    }
+
     async fn set_default_branch(&self, repo_name: &str, branch: &str) -> anyhow::Result<()> {
         println!(
             "Setting '{}' as default branch for '{}' repository",
@@ -85,15 +413,135 @@ impl Migrator {
         Ok(())
     }
 
-    pub async fn migrate(self) -> Result<(), anyhow::Error> {
-        let file = File::open(&self.migration_file)?;
-        let migration: Migration = serde_json::from_reader(file).with_context(|| format!("Error when parsing {} file.\nIs this a JSON file?\nDoes the version match the program version ({})?\nConsider re-generating the migration file with `wizard` subcommand.", &self.migration_file.display(), &self.version))?;
-        if migration.version != self.version {
-            return Err(anyhow!("Migration file version is not compatible with current version, expected: {}, found: {}", &self.version, migration.version));
+    async fn add_collaborators(
+        &mut self,
+        repo_name: &str,
+        collaborators: &[Collaborator],
+    ) -> anyhow::Result<()> {
+        println!(
+            "Adding {} collaborators to '{}' repository",
+            collaborators.len(),
+            repo_name
+        );
+        let pb = ProgressBar::new(collaborators.len() as u64);
+        pb.set_style(progress_bar_style());
+        let mut failures = vec![];
+        for collaborator in collaborators {
+            if let Err(e) = self
+                .github
+                .add_repository_collaborator(
+                    repo_name,
+                    &collaborator.login,
+                    &collaborator.permission,
+                )
+                .await
+            {
+                failures.push((&collaborator.login, e));
+            }
+            pb.inc(1);
+        }
+        pb.finish_with_message(format!(
+            "Added {} collaborators, {} failed",
+            collaborators.len() - failures.len(),
+            failures.len()
+        ));
+
+        for (login, e) in &failures {
+            eprintln!(
+                "Failed to add {} as collaborator of '{}' repository: {}",
+                login, repo_name, e
+            );
+        }
+        self.summary
+            .failures
+            .extend(failures.into_iter().map(|(login, e)| Failure {
+                repository: None,
+                error: redact(&format!(
+                    "Failed to add {} as collaborator of '{}' repository: {:#}",
+                    login, repo_name, e
+                )),
+            }));
+        Ok(())
+    }
+
+    async fn migrate_deploy_keys(&self, repo_name: &str, keys: &[DeployKey]) -> anyhow::Result<()> {
+        let existing_keys = self.github.get_deploy_keys(repo_name).await?;
+        let (already_added, to_add): (Vec<_>, Vec<_>) = keys.iter().partition(|key| {
+            existing_keys
+                .iter()
+                .any(|existing| same_public_key(&existing.key, &key.key))
+        });
+
+        let spinner = spinner::create_spinner(format!(
+            "Adding {} deploy keys to '{}' repository, skipping {} already added",
+            to_add.len(),
+            repo_name,
+            already_added.len()
+        ));
+        for key in &to_add {
+            self.github
+                .add_deploy_key(repo_name, &key.title, &key.key, true)
+                .await?;
+        }
+        spinner.finish_with_message(format!(
+            "Added {} deploy keys to '{}' repository, skipped {}",
+            to_add.len(),
+            repo_name,
+            already_added.len()
+        ));
+        if to_add.is_empty() {
+            return Ok(());
+        }
+        eprintln!(
+            "Warning: deploy keys of '{}' repository were added as read-only. Enable write access in GitHub if needed, and configure the private keys in the systems using them to access GitHub.",
+            repo_name
+        );
+        Ok(())
+    }
+
+    async fn create_codeowners(
+        &self,
+        repo_name: &str,
+        team_slugs: &[String],
+    ) -> anyhow::Result<()> {
+        let spinner = spinner::create_spinner(format!(
+            "Creating CODEOWNERS file in '{}' repository",
+            repo_name
+        ));
+        let owners = team_slugs
+            .iter()
+            .map(|slug| format!("@{}/{}", self.github.organization_name(), slug))
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.github
+            .put_file_contents(
+                repo_name,
+                ".github/CODEOWNERS",
+                &format!("* {}\n", owners),
+                "Add CODEOWNERS",
+            )
+            .await?;
+        spinner.finish_with_message(format!(
+            "Created CODEOWNERS file in '{}' repository",
+            repo_name
+        ));
+        Ok(())
+    }
+
+    pub async fn migrate(mut self) -> Result<(), anyhow::Error> {
+        let migration: Migration = migration_file::read(&self.migration_file).with_context(|| format!("Error when parsing {} file.\nIs this a JSON or YAML file?\nIs the version compatible with the program version ({})?\nConsider re-generating the migration file with `wizard` subcommand.", &self.migration_file.display(), &self.version))?;
+        if !self.ignore_version {
+            migration_file::check_version(&migration.version, &self.version)?;
         }
         let actions = migration.actions;
+        self.summary.skipped_repositories = migration.skipped;
+        self.target_names = target_names(&actions, &self.workspace_name);
+
+        let spinner = spinner::create_spinner("Verifying GitHub token...");
+        self.github.verify_token_scopes().await?;
+        spinner.finish_with_message("GitHub token verified");
 
-        println!("{}", describe_actions(&actions));
+        println!("{}", describe_action_entries(&actions));
 
         let confirmed = Confirm::with_prompt("Are you sure you want to migrate?").interact()?;
 
@@ -101,68 +549,268 @@ impl Migrator {
             return Err(anyhow!("Migration canceled"));
         }
 
+        if let Some(pre_hook) = &self.pre_hook {
+            self.run_hook("pre-migration", pre_hook, &[])?;
+        }
+
         let start = Instant::now();
-        for action in actions {
-            let _ = self.run(&action).await?;
+        let mut result = Ok(());
+        let enabled = actions
+            .iter()
+            .filter(|entry| entry.enabled)
+            .map(|entry| &entry.action)
+            .collect::<Vec<_>>();
+        for (idx, action) in enabled.iter().enumerate() {
+            show_actions_progress(idx, enabled.len(), action, start.elapsed());
+            if let Err(e) = self.run(action).await {
+                // Failed repositories are already in the summary
+                if e.downcast_ref::<PartialMigrationError>().is_none() {
+                    self.summary.failures.push(Failure {
+                        repository: None,
+                        error: redact(&format!("{:#}", e)),
+                    });
+                }
+                result = Err(e);
+                break;
+            }
         }
         let duration = start.elapsed();
 
+        if let Some(report_path) = &self.report_md {
+            if let Err(e) = self.summary.write_markdown(report_path, &actions, duration) {
+                eprintln!(
+                    "Warning: failed to write report to {}: {:#}",
+                    report_path.display(),
+                    e
+                );
+            }
+        }
+
+        if let Some(webhook_url) = &self.notify_webhook {
+            if let Err(e) = self.summary.notify(webhook_url, duration).await {
+                eprintln!(
+                    "Warning: failed to send notification to the webhook: {:#}",
+                    e
+                );
+            }
+        }
+
+        if let Some(post_hook) = &self.post_hook {
+            let status = if result.is_ok() && self.summary.is_success() {
+                "success"
+            } else {
+                "failure"
+            };
+            let hook_result =
+                self.run_hook("post-migration", post_hook, &[("MIGRATION_STATUS", status)]);
+            // The migration error is more important than the one of the hook
+            result = result.and(hook_result);
+        }
+
+        // Printed also when the migration failed, to check what was done so far and why it failed
+        if !self.summary.migrated_repositories.is_empty() {
+            println!("Migrated repositories:");
+            for repo in &self.summary.migrated_repositories {
+                println!("  - {}: {}", repo.name, repo.url);
+                if !repo.discrepancies.is_empty() {
+                    eprintln!(
+                        "    Warning: mirror does not match the local copy:\n{}",
+                        repo.discrepancies
+                            .iter()
+                            .map(|d| format!("      - {}", d))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    );
+                }
+            }
+        }
+        if !self.summary.skipped_repositories.is_empty() {
+            println!("Skipped repositories:");
+            for repo in &self.summary.skipped_repositories {
+                println!("  - {}: {}", repo.name, repo.reason);
+            }
+        }
+        if !self.summary.failures.is_empty() {
+            eprintln!("Failures:");
+            for failure in &self.summary.failures {
+                eprintln!(
+                    "  - {}: {}",
+                    failure.repository.as_deref().unwrap_or("-"),
+                    failure.error
+                );
+            }
+        }
+        result?;
+
         println!("Migration completed in {} seconds!", duration.as_secs());
 
         Ok(())
     }
 
-    async fn create_team(&self, name: &str, repositories: &[String]) -> Result<(), anyhow::Error> {
+    async fn create_repositories(
+        &self,
+        repositories: &[Repository],
+        init: Option<&RepositoryInit>,
+    ) -> anyhow::Result<()> {
+        println!("Creating {} repositories", repositories.len());
+        let init = init.cloned().unwrap_or_default();
+        for repo in repositories {
+            let name = repo.github_name(&self.workspace_name);
+            let spinner = spinner::create_spinner(format!("Creating {} repository", name));
+            let gh_repo = self
+                .github
+                .create_repository(&name, &repo.visibility(), &repo.metadata, &init)
+                .await?;
+            spinner.finish_with_message(format!("Created {}", gh_repo.url()));
+        }
+        Ok(())
+    }
+
+    async fn create_team(
+        &mut self,
+        name: &str,
+        repositories: &[String],
+        description: Option<&str>,
+        parent_team_slug: Option<&str>,
+    ) -> Result<(), anyhow::Error> {
+        let parent_team_id = match parent_team_slug {
+            Some(slug) => Some(self.parent_team_id(slug).await?),
+            None => None,
+        };
         let spinner = spinner::create_spinner(format!("Creating team {}", name));
-        self.github.create_team(name, repositories).await?;
+        let team = self
+            .github
+            .create_team(name, repositories, description, parent_team_id)
+            .await?;
         spinner.finish_with_message("Created!");
+        self.created_teams.insert(name.to_string(), team.slug);
+        self.summary.created_teams.push(CreatedTeam {
+            name: name.to_string(),
+            members: vec![],
+        });
         Ok(())
     }
 
-    async fn migrate_repositories(&self, repositories: &[Repository]) -> Result<(), anyhow::Error> {
+    async fn parent_team_id(&self, slug: &str) -> anyhow::Result<u32> {
+        let teams = self.github.get_teams().await?;
+        teams
+            .iter()
+            .find(|t| t.slug == slug)
+            .map(|t| t.id)
+            .ok_or_else(|| anyhow!("Parent team '{}' not found in GitHub", slug))
+    }
+
+    /// Name of the GitHub repository planned as `repository_name` by the wizard, replaced with
+    /// the `target_name` the repository is created with, so all the actions are done on the same repository.
+    fn repository_name(&self, repository_name: &str) -> String {
+        let name =
+            resolve_repository_name(&self.target_names, repository_name, &self.workspace_name);
+        github_full_name(name, &self.workspace_name, self.github.organization_name())
+    }
+
+    fn repository_names(&self, repositories_names: &[String]) -> Vec<String> {
+        repositories_names
+            .iter()
+            .map(|name| self.repository_name(name))
+            .collect()
+    }
+
+    /// Slug of the team, preferring the one returned by GitHub when the team was created
+    /// during this migration over the one computed by the wizard.
+    fn team_slug<'a>(&'a self, team_name: &str, planned_slug: &'a str) -> &'a str {
+        self.created_teams
+            .get(team_name)
+            .map_or(planned_slug, |slug| slug.as_str())
+    }
+
+    async fn migrate_repositories(
+        &mut self,
+        repositories: &[Repository],
+    ) -> Result<(), anyhow::Error> {
+        git::check_installed(self.verbose)?;
+
         println!("Migrating {} repositories", repositories.len());
-        let multi_progress = MultiProgress::new();
 
         let push_key = &self.git_config.push_ssh_key;
         let pull_key = &self.git_config.pull_ssh_key;
 
-        let tmp_dir = TempDir::new("migrate-bb-to-gh")?;
+        let keys_dir = git::KeysDir::new()?;
+
+        let passphrase = self.git_config.ssh_key_passphrase.as_deref();
+        let push_key_path = keys_dir.store_ssh_key("push", push_key, passphrase)?;
+        let pull_key_path = keys_dir.store_ssh_key("pull", pull_key, passphrase)?;
 
-        let push_key_path = self.store_ssh_key("push", push_key, tmp_dir.path())?;
-        let pull_key_path = self.store_ssh_key("pull", pull_key, tmp_dir.path())?;
+        // Counts steps of all the repositories, so the ETA is updated as each of them advances
+        let progress =
+            MigrationProgress::new(repositories.len(), self.mirror_options().steps_count());
 
         let handles = repositories.iter().map(|repo| {
-            Self::migrate_repository(
-                &self.github,
+            self.migrate_repository(
                 repo,
-                &multi_progress,
+                &progress,
+                keys_dir.path(),
                 &pull_key_path,
                 &push_key_path,
             )
         });
 
         let handles = futures::future::join_all(handles).await;
+        let mut outcomes = vec![];
         for h in handles {
-            let res = h.await?;
-            if let Err(e) = res {
-                eprintln!("Failed to migrate repository: {}", e)
-            }
+            outcomes.push(h.await?);
         }
 
-        multi_progress.clear()?;
-        Ok(())
-    }
+        progress.total.finish();
+        progress.bars.clear()?;
 
-    fn store_ssh_key(&self, name: &str, key: &str, path: &Path) -> Result<PathBuf, anyhow::Error> {
-        let file_path = path.join(name);
-        let mut key_file = File::create(&file_path)?;
-        key_file.write_all(key.as_ref())?;
+        let mut failures = vec![];
+        for outcome in &outcomes {
+            match &outcome.status {
+                MigrationStatus::Migrated { discrepancies } => {
+                    self.summary.migrated_repositories.push(MigratedRepository {
+                        name: outcome.repo.full_name.clone(),
+                        url: outcome.github_url.clone().unwrap_or_default(),
+                        discrepancies: discrepancies.clone(),
+                    });
+                }
+                MigrationStatus::Failed { step, error } => {
+                    self.summary.failures.push(Failure {
+                        repository: Some(outcome.repo.full_name.clone()),
+                        error: redact(&format!("{:#}", error)),
+                    });
+                    failures.push((outcome, step, error));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            return Ok(());
+        }
 
-        let mut perms = key_file.metadata()?.permissions();
-        perms.set_mode(0o400);
-        key_file.set_permissions(perms)?;
+        eprintln!(
+            "{} of {} repositories failed to migrate:",
+            failures.len(),
+            repositories.len()
+        );
+        for (outcome, step, error) in &failures {
+            eprintln!(
+                "  - {} (when {}, after {} seconds): {}",
+                outcome.repo.full_name,
+                step,
+                outcome.duration.as_secs(),
+                redact(&format!("{:#}", error))
+            );
+        }
 
-        Ok(file_path)
+        if self.continue_on_error {
+            Ok(())
+        } else {
+            Err(PartialMigrationError {
+                failed: failures.len(),
+                total: repositories.len(),
+            }
+            .into())
+        }
     }
 
     async fn assign_repositories_to_team(
@@ -172,87 +820,264 @@ impl Migrator {
         permission: &TeamRepositoryPermission,
         repositories: &[String],
     ) -> Result<(), anyhow::Error> {
+        let spinner =
+            spinner::create_spinner(format!("Fetching repositories of team {}", team_name));
+        let team_permissions = self
+            .github
+            .get_team_repositories(team_slug)
+            .await?
+            .into_iter()
+            .flat_map(|r| {
+                let permission = r.permissions.as_ref()?.highest()?;
+                Some((r.full_name.to_lowercase(), permission))
+            })
+            .collect::<HashMap<_, _>>();
+        spinner.finish_with_message(format!(
+            "Team {} has access to {} repositories",
+            team_name,
+            team_permissions.len()
+        ));
+
+        let (already_assigned, to_assign): (Vec<_>, Vec<_>) = repositories.iter().partition(|r| {
+            team_permissions
+                .get(&r.to_lowercase())
+                .is_some_and(|current| current >= permission)
+        });
+
         println!(
-            "Assigning {} repositories to team {} ({})",
-            repositories.len(),
+            "Assigning {} repositories to team {} ({}), skipping {} already assigned",
+            to_assign.len(),
             team_name,
-            permission
+            permission,
+            already_assigned.len()
         );
-        let pb = ProgressBar::new(repositories.len() as u64);
+        let pb = ProgressBar::new(to_assign.len() as u64);
         pb.set_style(progress_bar_style());
-        for repository in repositories {
+        for repository in to_assign {
             self.github
                 .assign_repository_to_team(team_slug, permission, repository)
                 .await?;
             pb.inc(1);
         }
+        pb.finish_with_message(format!(
+            "Assigned {} repositories, skipped {}",
+            pb.position(),
+            already_assigned.len()
+        ));
         Ok(())
     }
 
+    fn mirror_options(&self) -> MirrorOptions {
+        MirrorOptions {
+            verify: self.verify,
+            prune_refs: self.prune_refs,
+            verbose: self.verbose,
+            force_overwrite: self.force_overwrite,
+        }
+    }
+
     async fn migrate_repository(
-        github_api: &GithubApi,
+        &self,
         repository: &Repository,
-        multi_progress: &MultiProgress,
+        progress: &MigrationProgress,
+        work_dir: &Path,
         pull_key_path: &Path,
         push_key_path: &Path,
-    ) -> JoinHandle<Result<Repository, anyhow::Error>> {
-        let steps_count = 4;
-        let pb = multi_progress.add(ProgressBar::new(steps_count));
+    ) -> JoinHandle<MigrationOutcome> {
+        let options = self.mirror_options();
+        let steps_count = options.steps_count();
+        let pb = progress.bars.add(ProgressBar::new(steps_count));
         pb.set_prefix(format!("[{}] ", repository.full_name));
         pb.set_style(progress_bar_style());
 
         let repo = repository.clone();
+        let work_dir = work_dir.to_path_buf();
         let pull_key_path = pull_key_path.to_path_buf();
         let push_key_path = push_key_path.to_path_buf();
-        let github = github_api.clone();
-        tokio::spawn(async move {
-            let temp_dir = TempDir::new(&repo.full_name.to_owned().replace('/', "_"))?;
-            pb.set_message(format!("[1/{}] Cloning {}", steps_count, repo.full_name,));
-            let _ = Self::clone_mirror(&repo.clone_link, temp_dir.path(), &pull_key_path);
-            pb.inc(1);
+        let github = self.github.clone();
+        let github_name = repo.github_name(&self.workspace_name);
+        let total = progress.total.clone();
+        let span = tracing::info_span!("migrate_repository", repo = %repository.full_name);
+        let migration = async move {
+            let started = Instant::now();
+            let mut step = MigrationStep::Clone;
+            let mut github_url = None;
+            let advance = || {
+                pb.inc(1);
+                total.inc(1);
+            };
 
-            pb.set_message(format!(
-                "[2/{}] Creating {} repository in GitHub",
-                steps_count, repo.full_name
-            ));
-            let gh_repo = github
-                .create_repository(&repo.full_name.to_owned().replace("moodup/", ""))
-                .await?;
-            pb.inc(1);
+            let result = async {
+                // Clones are kept inside the keys directory, so they're also removed on interruption
+                let temp_dir =
+                    TempDir::new_in(&work_dir, &repo.full_name.to_owned().replace('/', "_"))?;
+                pb.set_message(format!("[1/{}] Cloning {}", steps_count, repo.full_name,));
+                let clone_progress = |line: &str| {
+                    pb.set_message(format!(
+                        "[1/{}] Cloning {}: {}",
+                        steps_count, repo.full_name, line
+                    ))
+                };
+                Self::clone_mirror(
+                    &repo.clone_link,
+                    temp_dir.path(),
+                    &pull_key_path,
+                    options.verbose.then_some(&clone_progress as &dyn Fn(&str)),
+                )?;
+                if options.prune_refs {
+                    pb.set_message(format!(
+                        "[1/{}] Pruning refs of {}",
+                        steps_count, repo.full_name
+                    ));
+                    git::prune_refs(temp_dir.path())?;
+                }
+                advance();
 
-            pb.set_message(format!(
-                "[3/{}] Mirroring {} repository to GitHub",
-                steps_count, repo.full_name
-            ));
-            let _ = Self::push_mirror(temp_dir.path(), &gh_repo.ssh_url, &push_key_path)?;
-            pb.inc(1);
+                step = MigrationStep::Create;
+                pb.set_message(format!(
+                    "[2/{}] Creating {} repository in GitHub",
+                    steps_count, repo.full_name
+                ));
+                let gh_repo = github
+                    .create_repository(
+                        &github_name,
+                        &repo.visibility(),
+                        &repo.metadata,
+                        // The mirror is pushed to the repository, so it has to be created without any commit
+                        &RepositoryInit::default(),
+                    )
+                    .await?;
+                github_url = Some(gh_repo.url());
+                advance();
 
-            pb.set_message(format!(
-                "[4/{}] Deleting {} repository from temp directory",
-                steps_count, repo.full_name
-            ));
-            temp_dir.close()?;
+                step = MigrationStep::Push;
+                pb.set_message(format!(
+                    "[3/{}] Mirroring {} repository to GitHub",
+                    steps_count, repo.full_name
+                ));
+                let push_progress = |line: &str| {
+                    pb.set_message(format!(
+                        "[3/{}] Mirroring {} repository to GitHub: {}",
+                        steps_count, repo.full_name, line
+                    ))
+                };
+                let push_progress = options.verbose.then_some(&push_progress as &dyn Fn(&str));
+                match &repo.branches {
+                    Some(branches) => Self::push_branches(
+                        temp_dir.path(),
+                        &gh_repo.ssh_url,
+                        &push_key_path,
+                        branches,
+                        push_progress,
+                    )?,
+                    None => {
+                        if !options.force_overwrite {
+                            let overwritten = Self::overwritten_remote_refs(
+                                temp_dir.path(),
+                                &gh_repo.ssh_url,
+                                &push_key_path,
+                            )?;
+                            if !overwritten.is_empty() {
+                                return Err(anyhow!(
+                                    "{} already has refs which are not in Bitbucket, pass --force-overwrite to overwrite them:\n{}",
+                                    gh_repo.full_name,
+                                    overwritten
+                                        .iter()
+                                        .map(|r| format!("  - {}", r))
+                                        .collect::<Vec<_>>()
+                                        .join("\n")
+                                ));
+                            }
+                        }
+                        Self::push_mirror(
+                            temp_dir.path(),
+                            &gh_repo.ssh_url,
+                            &push_key_path,
+                            push_progress,
+                        )?
+                    }
+                }
+                advance();
 
-            pb.finish_with_message("✅ Migrated successfully!");
+                let mut discrepancies = vec![];
+                if options.verify {
+                    step = MigrationStep::Verify;
+                    pb.set_message(format!(
+                        "[4/{}] Verifying {} mirror",
+                        steps_count, repo.full_name
+                    ));
+                    discrepancies = Self::verify_mirror(
+                        temp_dir.path(),
+                        &gh_repo.ssh_url,
+                        &push_key_path,
+                        repo.branches.as_deref(),
+                    )?;
+                    advance();
+                }
 
-            Ok(repo)
-        })
+                step = MigrationStep::Cleanup;
+                pb.set_message(format!(
+                    "[{}/{}] Deleting {} repository from temp directory",
+                    steps_count, steps_count, repo.full_name
+                ));
+                temp_dir.close()?;
+
+                Ok::<_, anyhow::Error>(discrepancies)
+            }
+            .await;
+
+            // Steps left after a failure (and the cleanup) are counted as done in the total
+            total.inc(steps_count.saturating_sub(pb.position()));
+            let status = match result {
+                Ok(discrepancies) => {
+                    if discrepancies.is_empty() {
+                        pb.finish_with_message(format!(
+                            "✅ Migrated successfully! {}",
+                            github_url.as_deref().unwrap_or_default()
+                        ));
+                    } else {
+                        pb.finish_with_message(format!(
+                            "⚠️ Migrated with {} mismatched refs",
+                            discrepancies.len()
+                        ));
+                    }
+                    MigrationStatus::Migrated { discrepancies }
+                }
+                Err(error) => {
+                    pb.abandon_with_message(format!("❌ Failed when {}", step));
+                    MigrationStatus::Failed { step, error }
+                }
+            };
+
+            MigrationOutcome {
+                repo,
+                github_url,
+                duration: started.elapsed(),
+                status,
+            }
+        };
+        tokio::spawn(migration.instrument(span))
     }
 
+    #[tracing::instrument(skip_all, fields(url = %remote_url))]
     fn clone_mirror(
         remote_url: &str,
         target_path: &Path,
         key_path: &Path,
+        progress: Option<&dyn Fn(&str)>,
     ) -> Result<(), anyhow::Error> {
-        let ssh_command = Self::prepare_ssh_command(key_path)?;
-        let clone_command = Command::new("git")
+        let ssh_command = git::prepare_ssh_command(key_path)?;
+        let mut command = git::command();
+        command
             .arg("-c")
             .arg(format!("core.sshCommand={}", ssh_command))
             .arg("clone")
-            .arg("--mirror")
-            .arg(remote_url)
-            .arg(target_path)
-            .output()?;
+            .arg("--mirror");
+        if progress.is_some() {
+            command.arg("--progress");
+        }
+        command.arg(remote_url).arg(target_path);
+        let clone_command = git::output(&mut command, progress)?;
 
         if !clone_command.status.success() {
             let err_output = String::from_utf8(clone_command.stderr)?;
@@ -268,28 +1093,25 @@ impl Migrator {
         Ok(())
     }
 
-    fn prepare_ssh_command(key_path: &Path) -> Result<String, anyhow::Error> {
-        let cmd = format!(
-            "ssh -i '{private_key_file}' -o IdentitiesOnly=yes -o StrictHostKeyChecking=no -o UserKnownHostsFile='/dev/null' -F '/dev/null'",
-            private_key_file = fs::canonicalize(key_path)?.display()
-        );
-        Ok(cmd)
-    }
-
+    #[tracing::instrument(skip_all, fields(url = %remote_url))]
     fn push_mirror(
         repo_path: &Path,
         remote_url: &str,
         key_path: &Path,
+        progress: Option<&dyn Fn(&str)>,
     ) -> Result<(), anyhow::Error> {
-        let ssh_command = Self::prepare_ssh_command(key_path)?;
-        let push_command = Command::new("git")
+        let ssh_command = git::prepare_ssh_command(key_path)?;
+        let mut command = git::command();
+        command
             .arg("-c")
             .arg(format!("core.sshCommand={}", ssh_command))
             .arg("push")
-            .arg("--mirror")
-            .arg(remote_url)
-            .current_dir(repo_path)
-            .output()?;
+            .arg("--mirror");
+        if progress.is_some() {
+            command.arg("--progress");
+        }
+        command.arg(remote_url).current_dir(repo_path);
+        let push_command = git::output(&mut command, progress)?;
 
         if !push_command.status.success() {
             let err_output = String::from_utf8(push_command.stderr)?;
@@ -305,21 +1127,153 @@ impl Migrator {
         Ok(())
     }
 
-    async fn run(&self, action: &Action) -> Result<(), anyhow::Error> {
+    /// Pushes only the given branches (and all tags), instead of mirroring all the refs
+    #[tracing::instrument(skip_all, fields(url = %remote_url))]
+    fn push_branches(
+        repo_path: &Path,
+        remote_url: &str,
+        key_path: &Path,
+        branches: &[String],
+        progress: Option<&dyn Fn(&str)>,
+    ) -> Result<(), anyhow::Error> {
+        let ssh_command = git::prepare_ssh_command(key_path)?;
+        let mut command = git::command();
+        command
+            .arg("-c")
+            .arg(format!("core.sshCommand={}", ssh_command))
+            .arg("push");
+        if progress.is_some() {
+            command.arg("--progress");
+        }
+        command
+            .arg(remote_url)
+            .args(
+                branches
+                    .iter()
+                    .map(|b| format!("refs/heads/{b}:refs/heads/{b}", b = b)),
+            )
+            .arg("refs/tags/*:refs/tags/*")
+            .current_dir(repo_path);
+        let push_command = git::output(&mut command, progress)?;
+
+        if !push_command.status.success() {
+            let err_output = String::from_utf8(push_command.stderr)?;
+            return Err(anyhow!(
+                "Error when pushing branches of {} to {}: {}\noutput: {}",
+                repo_path.display(),
+                remote_url,
+                push_command.status,
+                err_output
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns descriptions of refs on the remote which would be deleted or rewritten by the mirror push,
+    /// i.e. the ones which are missing or point to a different commit in the local copy.
+    ///
+    /// Refs managed by GitHub (`HEAD` and pull requests refs) are ignored.
+    fn overwritten_remote_refs(
+        repo_path: &Path,
+        remote_url: &str,
+        key_path: &Path,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let local_refs = git::local_refs(repo_path)?;
+        let remote_refs = git::remote_refs(repo_path, remote_url, key_path)?;
+
+        let mut overwritten = remote_refs
+            .iter()
+            .filter(|(name, _)| *name != "HEAD" && !name.starts_with("refs/pull/"))
+            .filter_map(|(name, sha)| match local_refs.get(name) {
+                None => Some(format!("{} would be deleted", name)),
+                Some(local_sha) if local_sha != sha => Some(format!(
+                    "{} would be moved from {} to {}",
+                    name, sha, local_sha
+                )),
+                Some(_) => None,
+            })
+            .collect::<Vec<_>>();
+        overwritten.sort();
+
+        Ok(overwritten)
+    }
+
+    /// Returns descriptions of refs which are missing or point to a different commit on the remote.
+    ///
+    /// When only some of the branches were pushed, other branches are not verified.
+    fn verify_mirror(
+        repo_path: &Path,
+        remote_url: &str,
+        key_path: &Path,
+        branches: Option<&[String]>,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let local_refs = git::local_refs(repo_path)?;
+        let remote_refs = git::remote_refs(repo_path, remote_url, key_path)?;
+
+        let mut discrepancies = local_refs
+            .iter()
+            .filter(|(name, _)| match branches {
+                Some(branches) => {
+                    name.starts_with("refs/tags/")
+                        || branches
+                            .iter()
+                            .any(|b| **name == format!("refs/heads/{}", b))
+                }
+                None => true,
+            })
+            .filter_map(|(name, sha)| match remote_refs.get(name) {
+                None => Some(format!("{} is missing", name)),
+                Some(remote_sha) if remote_sha != sha => Some(format!(
+                    "{} points to {} instead of {}",
+                    name, remote_sha, sha
+                )),
+                Some(_) => None,
+            })
+            .collect::<Vec<_>>();
+        discrepancies.sort();
+
+        Ok(discrepancies)
+    }
+
+    // Only the first line of the description, as it lists all the repositories in the next ones
+    #[tracing::instrument(
+        skip_all,
+        fields(action = action.describe().lines().next().unwrap_or_default())
+    )]
+    async fn run(&mut self, action: &Action) -> Result<(), anyhow::Error> {
         match action {
-            Action::CreateTeam { name, repositories } => {
-                self.create_team(name, repositories).await?
+            Action::CreateTeam {
+                name,
+                repositories,
+                description,
+                parent_team_slug,
+            } => {
+                let repositories = self.repository_names(repositories);
+                self.create_team(
+                    name,
+                    &repositories,
+                    description.as_deref(),
+                    parent_team_slug.as_deref(),
+                )
+                .await?
             }
             Action::MigrateRepositories { repositories } => {
                 self.migrate_repositories(repositories).await?
             }
+            Action::CreateRepositories { repositories, init } => {
+                self.create_repositories(repositories, init.as_ref())
+                    .await?
+            }
             Action::AssignRepositoriesToTeam {
                 team_name,
                 team_slug,
                 permission,
                 repositories,
             } => {
-                self.assign_repositories_to_team(team_name, team_slug, permission, repositories)
+                let repositories = self.repository_names(repositories);
+                let team_slug = self.team_slug(team_name, team_slug);
+                self.assign_repositories_to_team(team_name, team_slug, permission, &repositories)
                     .await?
             }
             Action::AddMembersToTeam {
@@ -327,20 +1281,236 @@ impl Migrator {
                 team_slug,
                 members,
             } => {
-                self.add_members_to_team(team_name, team_slug, members)
+                let team_slug = self.team_slug(team_name, team_slug).to_string();
+                self.add_members_to_team(team_name, &team_slug, members)
                     .await?
             }
             Action::SetRepositoryDefaultBranch {
                 repository_name,
                 branch,
-            } => self.set_default_branch(repository_name, branch).await?,
+                rename_to,
+            } => {
+                let repository_name = &self.repository_name(repository_name);
+                let branch = match rename_to {
+                    Some(new_name) => {
+                        self.rename_branch(repository_name, branch, new_name)
+                            .await?
+                    }
+                    None => branch.clone(),
+                };
+                self.set_default_branch(repository_name, &branch).await?;
+                self.summary.default_branches.push(DefaultBranchChange {
+                    repository: repository_name.clone(),
+                    branch,
+                });
+            }
+            Action::AddRepositoriesToExistingTeam {
+                team_name,
+                team_slug,
+                permission,
+                repositories,
+            } => {
+                let repositories = self.repository_names(repositories);
+                self.assign_repositories_to_team(team_name, team_slug, permission, &repositories)
+                    .await?
+            }
+            Action::CreateCodeowners {
+                repository_name,
+                team_slugs,
+            } => {
+                let repository_name = self.repository_name(repository_name);
+                self.create_codeowners(&repository_name, team_slugs).await?
+            }
+            Action::AddCollaborators {
+                repository_name,
+                collaborators,
+            } => {
+                let repository_name = self.repository_name(repository_name);
+                self.add_collaborators(&repository_name, collaborators)
+                    .await?
+            }
+            Action::MigrateDeployKeys {
+                repository_name,
+                keys,
+            } => {
+                let repository_name = self.repository_name(repository_name);
+                self.migrate_deploy_keys(&repository_name, keys).await?
+            }
+            Action::SetRepositorySettings {
+                repository_name,
+                settings,
+            } => {
+                let repository_name = &self.repository_name(repository_name);
+                let spinner = spinner::create_spinner(format!(
+                    "Changing settings of '{}' repository",
+                    repository_name
+                ));
+                self.github
+                    .update_repository_settings(repository_name, settings)
+                    .await?;
+                spinner.finish_with_message(format!(
+                    "Changed settings of '{}' repository",
+                    repository_name
+                ));
+            }
+            Action::RenameRepository { from_name, to_name } => {
+                let from_name = &self.repository_name(from_name);
+                let spinner = spinner::create_spinner(format!(
+                    "Renaming '{}' repository to '{}'",
+                    from_name, to_name
+                ));
+                let gh_repo = self.github.rename_repository(from_name, to_name).await?;
+                if let Some(migrated) = self.summary.migrated_repositories.iter_mut().find(|r| {
+                    let migrated_name = r.url.rsplit('/').next().unwrap_or_default();
+                    from_name.rsplit('/').next() == Some(migrated_name)
+                }) {
+                    migrated.url = gh_repo.url();
+                }
+                spinner.finish_with_message(format!(
+                    "Renamed '{}' repository to {}",
+                    from_name,
+                    gh_repo.url()
+                ));
+            }
         }
         Ok(())
     }
 }
 
+/// Target names of the repositories by their (lowercase) names planned by the wizard.
+///
+/// Also from the disabled actions, as the repositories could be created by the previous migration.
+pub(crate) fn target_names(
+    actions: &[ActionEntry<Action>],
+    workspace: &str,
+) -> HashMap<String, String> {
+    actions
+        .iter()
+        .flat_map(|entry| match &entry.action {
+            Action::MigrateRepositories { repositories }
+            | Action::CreateRepositories { repositories, .. } => repositories.as_slice(),
+            _ => &[],
+        })
+        .filter_map(|r| {
+            let target_name = r.target_name.clone()?;
+            let planned = github_repository_name(&r.full_name, workspace);
+            Some((planned.to_lowercase(), target_name))
+        })
+        .collect()
+}
+
+/// Name (without the owner) of the GitHub repository planned as `repository_name` by the wizard,
+/// replaced with the `target_name` the repository is created with.
+pub(crate) fn resolve_repository_name<'a>(
+    target_names: &'a HashMap<String, String>,
+    repository_name: &'a str,
+    workspace: &str,
+) -> &'a str {
+    let planned = github_repository_name(repository_name, workspace);
+    target_names
+        .get(&planned.to_lowercase())
+        .map_or(planned, |target_name| target_name.as_str())
+}
+
+/// Compares type and body of the public SSH keys, as GitHub returns them without the comment
+fn same_public_key(a: &str, b: &str) -> bool {
+    a.split_whitespace()
+        .take(2)
+        .eq(b.split_whitespace().take(2))
+}
+
+/// Draws the overall progress once before each action, as a bar redrawn during the action
+/// would overwrite the output of the action
+fn show_actions_progress(idx: usize, count: usize, action: &Action, elapsed: Duration) {
+    let pb = ProgressBar::new(count as u64)
+        .with_position(idx as u64 + 1)
+        .with_elapsed(elapsed);
+    pb.set_style(
+        ProgressStyle::with_template("[{elapsed}] {bar:20.green/blue} action {pos}/{len}: {msg}")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    let description = action.describe();
+    pb.abandon_with_message(description.lines().next().unwrap_or_default().to_string());
+}
+
 fn progress_bar_style() -> ProgressStyle {
-    ProgressStyle::with_template("[{elapsed}] {bar:20.cyan/blue} {pos:>7}/{len:7} {msg}")
-        .unwrap()
-        .progress_chars("##-")
+    ProgressStyle::with_template(
+        "[{elapsed}] {bar:20.cyan/blue} {pos:>7}/{len:7} {per_sec} ETA {eta} {msg}",
+    )
+    .unwrap()
+    .progress_chars("##-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrator() -> Migrator {
+        let config: Config = serde_yaml::from_str(
+            r#"
+git:
+  push_ssh_key: key
+  pull_ssh_key: key
+bitbucket:
+  username: user
+  password: app-password
+  workspace_name: acme
+github:
+  username: user
+  password: token
+  organization_name: org
+circleci:
+  token: token
+  bitbucket_org_id: bitbucket-org
+  github_org_id: github-org
+"#,
+        )
+        .unwrap();
+        Migrator::new(Path::new("migration.json"), "0.0.0", config)
+    }
+
+    #[test]
+    fn repository_name_follows_target_name() {
+        let repositories: Vec<Repository> = serde_json::from_value(serde_json::json!([
+            {
+                "clone_link": "git@bitbucket.org:acme/API.git",
+                "name": "API",
+                "full_name": "acme/API",
+                "target_name": "api-server",
+            },
+            {
+                "clone_link": "git@bitbucket.org:acme/web.git",
+                "name": "web",
+                "full_name": "acme/web",
+            },
+        ]))
+        .unwrap();
+        let mut migrator = migrator();
+        migrator.target_names = target_names(
+            &[ActionEntry::new(Action::MigrateRepositories {
+                repositories,
+            })],
+            "acme",
+        );
+
+        assert_eq!(migrator.repository_name("org/API"), "org/api-server");
+        assert_eq!(migrator.repository_name("org/web"), "org/web");
+    }
+
+    #[test]
+    fn public_keys_are_compared_without_comment() {
+        assert!(same_public_key(
+            "ssh-ed25519 AAAAC3Nza",
+            "ssh-ed25519 AAAAC3Nza deploy@ci"
+        ));
+        assert!(!same_public_key(
+            "ssh-ed25519 AAAAC3Nza",
+            "ssh-ed25519 AAAAC3Nzb"
+        ));
+        assert!(!same_public_key(
+            "ssh-rsa AAAAC3Nza",
+            "ssh-ed25519 AAAAC3Nza"
+        ));
+    }
 }
@@ -1,7 +1,12 @@
 mod action;
+mod edit;
 mod migrator;
+mod status;
+mod summary;
 mod wizard;
 
 pub use action::describe_actions;
+pub use edit::edit;
 pub use migrator::Migrator;
+pub use status::status;
 pub use wizard::{Wizard, WizardResult};
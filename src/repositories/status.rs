@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::config::Config;
+use crate::github::GithubApi;
+use crate::repositories::action::Action;
+use crate::repositories::migrator::{resolve_repository_name, target_names, Migration};
+use crate::{migration_file, spinner};
+
+struct StatusItem {
+    name: String,
+    present: bool,
+}
+
+/// Compares the repositories and teams planned in the migration file with the ones existing on GitHub.
+///
+/// Returns `true` when all of them are present.
+pub async fn status(migration_file: &Path, config: &Config) -> anyhow::Result<bool> {
+    let migration: Migration = migration_file::read(migration_file)
+        .with_context(|| format!("Error when parsing {} file", migration_file.display()))?;
+
+    let github = GithubApi::new(&config.github);
+    let workspace = &config.bitbucket.workspace_name;
+
+    let spinner = spinner::create_spinner("Checking migration status on GitHub...");
+    let teams = github.get_current_teams().await?;
+
+    // Renamed repositories are looked up by their new names
+    let target_names = target_names(migration.actions(), workspace);
+    let renames = migration
+        .enabled_actions()
+        .filter_map(|action| match action {
+            Action::RenameRepository { from_name, to_name } => {
+                let name = resolve_repository_name(&target_names, from_name, workspace);
+                Some((name.to_lowercase(), to_name.as_str()))
+            }
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut items = vec![];
+    for action in migration.enabled_actions() {
+        match action {
+            Action::MigrateRepositories { repositories }
+            | Action::CreateRepositories { repositories, .. } => {
+                for repository in repositories {
+                    let mut name = repository.github_name(workspace);
+                    if let Some(new_name) = renames.get(&name.to_lowercase()) {
+                        name = new_name.to_string();
+                    }
+                    let present = github.get_repository(&name).await?.is_some();
+                    items.push(StatusItem {
+                        name: format!("Repository {}", name),
+                        present,
+                    });
+                }
+            }
+            Action::CreateTeam { name, .. } => {
+                let present = teams.iter().any(|t| t.name.eq_ignore_ascii_case(name));
+                items.push(StatusItem {
+                    name: format!("Team {}", name),
+                    present,
+                });
+            }
+            _ => {}
+        }
+    }
+    spinner.finish_and_clear();
+
+    for item in &items {
+        let mark = if item.present { "x" } else { " " };
+        let state = if item.present { "present" } else { "missing" };
+        println!("[{}] {} ({})", mark, item.name, state);
+    }
+
+    Ok(items.iter().all(|i| i.present))
+}
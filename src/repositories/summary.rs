@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::api::http_client;
+use crate::migration_file::ActionEntry;
+use crate::repositories::action::{describe_action_entries, Action};
+use crate::repositories::migrator::SkippedRepository;
+
+/// Outcome of the migration, collected while running the actions
+#[derive(Debug, Default)]
+pub(crate) struct Summary {
+    pub(crate) migrated_repositories: Vec<MigratedRepository>,
+    /// Repositories left out in the wizard
+    pub(crate) skipped_repositories: Vec<SkippedRepository>,
+    pub(crate) created_teams: Vec<CreatedTeam>,
+    pub(crate) default_branches: Vec<DefaultBranchChange>,
+    pub(crate) failures: Vec<Failure>,
+}
+
+#[derive(Debug)]
+pub(crate) struct MigratedRepository {
+    /// Full name of the repository in Bitbucket
+    pub(crate) name: String,
+    /// URL of the repository in GitHub
+    pub(crate) url: String,
+    /// Refs of the mirror which do not match the local copy
+    pub(crate) discrepancies: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct CreatedTeam {
+    pub(crate) name: String,
+    /// Members successfully added to the team
+    pub(crate) members: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct DefaultBranchChange {
+    pub(crate) repository: String,
+    pub(crate) branch: String,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct Failure {
+    /// Name of the repository which failed to migrate, `None` when other action failed
+    pub(crate) repository: Option<String>,
+    pub(crate) error: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Success,
+    Failure,
+}
+
+#[derive(Serialize, Debug)]
+struct Notification<'a> {
+    status: Status,
+    migrated_repositories: usize,
+    skipped_repositories: usize,
+    created_teams: usize,
+    failures: usize,
+    elapsed_seconds: u64,
+    errors: &'a [Failure],
+    /// Refs not matching the local copy, by the name of the migrated repository
+    discrepancies: BTreeMap<&'a str, &'a [String]>,
+}
+
+impl Summary {
+    pub(crate) fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// POSTs counts from the summary as JSON to the webhook URL
+    pub(crate) async fn notify(&self, webhook_url: &str, elapsed: Duration) -> anyhow::Result<()> {
+        let notification = Notification {
+            status: if self.is_success() {
+                Status::Success
+            } else {
+                Status::Failure
+            },
+            migrated_repositories: self.migrated_repositories.len(),
+            skipped_repositories: self.skipped_repositories.len(),
+            created_teams: self.created_teams.len(),
+            failures: self.failures.len(),
+            elapsed_seconds: elapsed.as_secs(),
+            errors: &self.failures,
+            discrepancies: self
+                .migrated_repositories
+                .iter()
+                .filter(|repo| !repo.discrepancies.is_empty())
+                .map(|repo| (repo.name.as_str(), repo.discrepancies.as_slice()))
+                .collect(),
+        };
+
+        http_client()
+            .post(webhook_url)
+            .json(&notification)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Writes the plan and the outcome of the migration in Markdown format
+    pub(crate) fn write_markdown(
+        &self,
+        path: &Path,
+        actions: &[ActionEntry<Action>],
+        elapsed: Duration,
+    ) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_markdown(actions, elapsed)?)?;
+        Ok(())
+    }
+
+    fn to_markdown(
+        &self,
+        actions: &[ActionEntry<Action>],
+        elapsed: Duration,
+    ) -> Result<String, std::fmt::Error> {
+        let mut md = String::new();
+        writeln!(md, "# Migration report")?;
+        writeln!(md)?;
+        writeln!(
+            md,
+            "**Status:** {} (in {} seconds)",
+            if self.is_success() {
+                "success"
+            } else {
+                "failure"
+            },
+            elapsed.as_secs()
+        )?;
+
+        writeln!(md)?;
+        writeln!(md, "## Plan")?;
+        writeln!(md)?;
+        writeln!(md, "```text")?;
+        writeln!(md, "{}", describe_action_entries(actions))?;
+        writeln!(md, "```")?;
+
+        if !self.migrated_repositories.is_empty() {
+            writeln!(md)?;
+            writeln!(md, "## Migrated repositories")?;
+            writeln!(md)?;
+            writeln!(md, "| Bitbucket | GitHub |")?;
+            writeln!(md, "|-----------|--------|")?;
+            for repo in &self.migrated_repositories {
+                writeln!(md, "| {} | <{}> |", repo.name, repo.url)?;
+            }
+
+            let mismatched = self
+                .migrated_repositories
+                .iter()
+                .filter(|repo| !repo.discrepancies.is_empty())
+                .collect::<Vec<_>>();
+            if !mismatched.is_empty() {
+                writeln!(md)?;
+                writeln!(md, "### Mirror discrepancies")?;
+                for repo in mismatched {
+                    writeln!(md)?;
+                    writeln!(md, "{}:", repo.name)?;
+                    writeln!(md)?;
+                    for discrepancy in &repo.discrepancies {
+                        writeln!(md, "- {}", discrepancy)?;
+                    }
+                }
+            }
+        }
+
+        if !self.skipped_repositories.is_empty() {
+            writeln!(md)?;
+            writeln!(md, "## Skipped repositories")?;
+            writeln!(md)?;
+            writeln!(md, "| Repository | Reason |")?;
+            writeln!(md, "|------------|--------|")?;
+            for repo in &self.skipped_repositories {
+                writeln!(md, "| {} | {} |", repo.name, repo.reason)?;
+            }
+        }
+
+        if !self.created_teams.is_empty() {
+            writeln!(md)?;
+            writeln!(md, "## Created teams")?;
+            writeln!(md)?;
+            writeln!(md, "| Team | Members |")?;
+            writeln!(md, "|------|---------|")?;
+            for team in &self.created_teams {
+                writeln!(md, "| {} | {} |", team.name, team.members.join(", "))?;
+            }
+        }
+
+        if !self.default_branches.is_empty() {
+            writeln!(md)?;
+            writeln!(md, "## Default branches")?;
+            writeln!(md)?;
+            writeln!(md, "| Repository | Default branch |")?;
+            writeln!(md, "|------------|----------------|")?;
+            for change in &self.default_branches {
+                writeln!(md, "| {} | {} |", change.repository, change.branch)?;
+            }
+        }
+
+        if !self.failures.is_empty() {
+            writeln!(md)?;
+            writeln!(md, "## Failures")?;
+            writeln!(md)?;
+            writeln!(md, "| Repository | Error |")?;
+            writeln!(md, "|------------|-------|")?;
+            for failure in &self.failures {
+                writeln!(
+                    md,
+                    "| {} | {} |",
+                    failure.repository.as_deref().unwrap_or("-"),
+                    failure.error.replace('|', "\\|").replace('\n', " ")
+                )?;
+            }
+        }
+
+        Ok(md)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_lists_mirror_discrepancies() {
+        let summary = Summary {
+            migrated_repositories: vec![
+                MigratedRepository {
+                    name: "acme/api".to_string(),
+                    url: "https://github.com/org/api".to_string(),
+                    discrepancies: vec!["refs/heads/main differs".to_string()],
+                },
+                MigratedRepository {
+                    name: "acme/web".to_string(),
+                    url: "https://github.com/org/web".to_string(),
+                    discrepancies: vec![],
+                },
+            ],
+            ..Summary::default()
+        };
+
+        let md = summary.to_markdown(&[], Duration::from_secs(1)).unwrap();
+
+        assert!(md.contains("### Mirror discrepancies\n\nacme/api:\n\n- refs/heads/main differs\n"));
+        assert!(!md.contains("acme/web:"));
+    }
+}
@@ -1,24 +1,49 @@
-use std::{collections::HashSet, fs::File, path::PathBuf};
+use std::fs;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use crate::{
     bitbucket::{self, BitbucketApi, Repository as BitbucketRepository},
-    github::{GithubApi, Repository as GitHubRepository, TeamRepositoryPermission},
-    spinner,
+    github::{
+        GithubApi, RepositoryInit, RepositorySettings, RepositoryVisibility,
+        TeamRepositoryPermission,
+    },
+    migration_file, spinner,
 };
 
 use crate::bitbucket::{Branch, Repository};
 use crate::config::{BitbucketConfig, GitHubConfig};
 use crate::github::Team;
 use crate::prompts::{Confirm, FuzzySelect, Input, MultiSelect, Select};
-use crate::repositories::action::Action;
-use crate::repositories::migrator::Migration;
-use anyhow::{anyhow, bail};
+use crate::repositories::action::{
+    dedupe_actions, github_full_name, Action, Collaborator, DeployKey,
+    Repository as MigrationRepository,
+};
+use crate::repositories::migrator::{Migration, Selection, SkippedRepository};
+use crate::teams_file::TeamsFile;
+use crate::user_map::UserMap;
+use anyhow::{anyhow, bail, Context};
+use futures::{StreamExt, TryStreamExt};
+use regex::Regex;
 
 pub struct Wizard {
     output_path: PathBuf,
     version: String,
     bitbucket: BitbucketApi,
     github: GithubApi,
+    all_repositories: bool,
+    user_map: Option<UserMap>,
+    repos_file: Option<PathBuf>,
+    default_branch_name: Option<String>,
+    since: Option<String>,
+    from: Option<PathBuf>,
+    keep_source_default: bool,
+    filter: Option<Regex>,
+    exclude: Option<Regex>,
+    project_key: Option<String>,
+    teams_file: Option<TeamsFile>,
 }
 
 #[derive(Debug)]
@@ -39,22 +64,115 @@ impl Wizard {
             version: version.to_owned(),
             bitbucket: BitbucketApi::new(&bitbucket_cfg),
             github: GithubApi::new(&github_config),
+            all_repositories: false,
+            user_map: None,
+            repos_file: None,
+            default_branch_name: None,
+            since: None,
+            from: None,
+            keep_source_default: false,
+            filter: None,
+            exclude: None,
+            project_key: None,
+            teams_file: None,
         }
     }
 
+    /// Selects all repositories of the chosen project, skipping the interactive selection.
+    pub fn all_repositories(&mut self, all_repositories: bool) -> &mut Self {
+        self.all_repositories = all_repositories;
+        self
+    }
+
+    /// Preselects team members based on the Bitbucket committers, translated to GitHub logins with the map.
+    pub fn user_map(&mut self, user_map: UserMap) -> &mut Self {
+        self.user_map = Some(user_map);
+        self
+    }
+
+    /// Reads names of the repositories to migrate from the file, skipping project and repositories selection.
+    pub fn repos_file(&mut self, repos_file: &Path) -> &mut Self {
+        self.repos_file = Some(repos_file.to_path_buf());
+        self
+    }
+
+    /// Renames the selected default branches to the given name (e.g. `main`) during the migration.
+    pub fn default_branch_name(&mut self, name: &str) -> &mut Self {
+        self.default_branch_name = Some(name.to_string());
+        self
+    }
+
+    /// Skips repositories of the project which were not updated since the date (in `YYYY-MM-DD` format).
+    pub fn since(&mut self, date: &str) -> &mut Self {
+        self.since = Some(date.to_string());
+        self
+    }
+
+    /// Starts with the repositories chosen in the previous wizard run, saved in the migration file.
+    pub fn from_migration(&mut self, migration_file: &Path) -> &mut Self {
+        self.from = Some(migration_file.to_path_buf());
+        self
+    }
+
+    /// Creates the teams defined in the file, instead of asking for the new and existing teams.
+    pub fn teams_file(&mut self, teams_file: TeamsFile) -> &mut Self {
+        self.teams_file = Some(teams_file);
+        self
+    }
+
+    /// Keeps default branches of the repositories from Bitbucket, instead of asking for the new ones.
+    pub fn keep_source_default(&mut self, keep_source_default: bool) -> &mut Self {
+        self.keep_source_default = keep_source_default;
+        self
+    }
+
+    /// Keeps only repositories of the project with names matching the pattern.
+    pub fn filter(&mut self, pattern: Regex) -> &mut Self {
+        self.filter = Some(pattern);
+        self
+    }
+
+    /// Skips repositories of the project with names matching the pattern, applied after the `filter`.
+    pub fn exclude(&mut self, pattern: Regex) -> &mut Self {
+        self.exclude = Some(pattern);
+        self
+    }
+
+    /// Uses the Bitbucket project with the key, skipping the interactive project selection.
+    pub fn project(&mut self, key: &str) -> &mut Self {
+        self.project_key = Some(key.to_owned());
+        self
+    }
+
     pub async fn run(&self) -> Result<WizardResult, anyhow::Error> {
         println!("Welcome to Bitbucket-GitHub Migration Wizard!");
-        let project = self.select_project().await?;
-        let bb_repos = self.select_repositories(&project).await?;
+        if self.teams_file.is_some() && self.github.is_user_account() {
+            bail!("Teams file cannot be used, as the repositories are migrated to a user account");
+        }
+        self.verify_github_token().await?;
+        let (project_name, bb_repos) = match (&self.from, &self.repos_file) {
+            (Some(migration_file), _) => self.repositories_from_migration(migration_file).await?,
+            (None, Some(repos_file)) => {
+                let repositories = self.repositories_from_file(repos_file).await?;
+                let project_name = repositories
+                    .iter()
+                    .find_map(|r| r.project.as_ref())
+                    .map(|p| p.name.clone())
+                    .unwrap_or_default();
+                (project_name, repositories)
+            }
+            (None, None) => {
+                let project = self.select_project().await?;
+                let repositories = self.select_repositories(&project).await?;
+                (project.name, repositories)
+            }
+        };
 
         let mut actions = vec![];
 
-        let repositories_names: Vec<String> =
-            bb_repos.iter().map(|r| r.full_name.to_owned()).collect();
-
-        let gh_repos = self.fetch_github_repositories().await?;
-        let already_migrated = Self::already_migrated_repo_names(&bb_repos, &gh_repos);
+        let already_migrated = self.already_migrated_repo_names(&bb_repos).await?;
         let repositories = Self::select_repositories_to_continue(&bb_repos, &already_migrated)?;
+        let skipped = Self::skipped_repositories(&bb_repos, &repositories, &already_migrated);
 
         if repositories.is_empty() {
             bail!("No repositories to take actions on, exiting...");
@@ -67,34 +185,67 @@ impl Wizard {
                     .map(|r| format!("  - {}", r.full_name))
                     .collect::<Vec<_>>()
                     .join("\n")
-            )
+            );
         }
-
-        if let Some(migrate_action) = Self::ask_clone_repos(&repositories)? {
-            actions.push(migrate_action);
+        if !skipped.is_empty() {
+            println!(
+                "Skipped {} repositories:\n{}",
+                skipped.len(),
+                skipped
+                    .iter()
+                    .map(|r| format!("  - {} ({})", r.name, r.reason))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
         }
 
-        let teams = self.fetch_github_teams().await?;
+        // Skipped repositories (e.g. already existing on GitHub) are left untouched by the other actions
+        let github_names: Vec<String> = repositories
+            .iter()
+            .map(|r| self.github_full_name(&r.full_name))
+            .collect();
 
-        println!("These teams already exist on GitHub:");
-        teams.iter().for_each(|t| println!("  - {}", t.name));
+        actions.extend(self.ask_clone_repos(&repositories).await?);
 
-        if let Some(new_team) = self
-            .ask_create_team(&project.name, &repositories_names, &teams)
-            .await?
-        {
-            actions.extend(new_team);
+        if let Some(deploy_keys_actions) = self.ask_migrate_deploy_keys(&repositories).await? {
+            actions.extend(deploy_keys_actions);
         }
 
-        if let Some(team_actions) = self.ask_additional_teams(&repositories_names, &teams)? {
-            actions.extend(team_actions);
+        if let Some(settings_actions) = Self::ask_repository_settings(&github_names)? {
+            actions.extend(settings_actions);
         }
 
-        if let Some(branch_actions) = self.ask_change_default_branch(&repositories).await? {
+        if self.github.is_user_account() {
+            println!("Skipping teams and collaborators, as the repositories are migrated to a user account");
+        } else {
+            actions.extend(
+                self.ask_access(&project_name, &repositories, &github_names)
+                    .await?,
+            );
+        }
+
+        if self.keep_source_default {
+            actions.extend(self.source_default_branches(&repositories, &actions));
+        } else if let Some(branch_actions) = self.ask_change_default_branch(&repositories).await? {
             actions.extend(branch_actions);
         }
 
-        let migration = Migration::new(&self.version, &actions);
+        if let Some(codeowners_actions) = Self::ask_create_codeowners(&github_names, &actions)? {
+            actions.extend(codeowners_actions);
+        }
+
+        // Other actions use the names before renaming, so the repositories are renamed at the end
+        if let Some(rename_actions) = self.ask_rename_repositories(&repositories)? {
+            actions.extend(rename_actions);
+        }
+
+        let actions = dedupe_actions(actions);
+        let mut migration = Migration::new(&self.version, &actions);
+        migration.set_selection(Some(Selection {
+            project: project_name,
+            repositories: repositories.iter().map(|r| r.full_name.clone()).collect(),
+        }));
+        migration.set_skipped(skipped);
         self.save_migration_file(&migration)?;
 
         Ok(WizardResult {
@@ -103,6 +254,65 @@ impl Wizard {
         })
     }
 
+    /// Asks for teams and collaborators having access to the repositories in the organization,
+    /// `repositories_names` being the names of the repositories in GitHub
+    async fn ask_access(
+        &self,
+        project_name: &str,
+        repositories: &[BitbucketRepository],
+        repositories_names: &[String],
+    ) -> anyhow::Result<Vec<Action>> {
+        let mut actions = vec![];
+
+        let teams = self.fetch_github_teams().await?;
+
+        println!("These teams already exist on GitHub:");
+        teams.iter().for_each(|t| println!("  - {}", t.name));
+
+        let suggested_members = self.suggested_members(repositories).await?;
+
+        if let Some(teams_file) = &self.teams_file {
+            actions.extend(self.teams_file_actions(teams_file, &teams).await?);
+        } else {
+            if let Some(new_team) = self
+                .ask_create_team(project_name, repositories_names, &teams, &suggested_members)
+                .await?
+            {
+                actions.extend(new_team);
+            }
+
+            if let Some(team_actions) = self.ask_additional_teams(repositories_names, &teams)? {
+                actions.extend(team_actions);
+            }
+        }
+
+        if let Some(collaborators_actions) = self
+            .ask_add_collaborators(repositories_names, &suggested_members)
+            .await?
+        {
+            actions.extend(collaborators_actions);
+        }
+
+        Ok(actions)
+    }
+
+    /// Owner-qualified name of the GitHub repository the Bitbucket repository is migrated to
+    fn github_full_name(&self, bitbucket_full_name: &str) -> String {
+        github_full_name(
+            bitbucket_full_name,
+            self.bitbucket.workspace_name(),
+            self.github.organization_name(),
+        )
+    }
+
+    async fn verify_github_token(&self) -> anyhow::Result<()> {
+        let spinner = spinner::create_spinner("Verifying GitHub token...");
+        self.github.verify_token_scopes().await?;
+        spinner.finish_with_message("GitHub token verified");
+
+        Ok(())
+    }
+
     async fn ask_change_default_branch(
         &self,
         repositories: &[Repository],
@@ -121,10 +331,9 @@ impl Wizard {
                 println!("No repositories selected, skipping changing default branch...");
                 return Ok(None);
             }
+            let repos_branches = self.fetch_repos_branches(&for_change).await?;
             let mut actions = vec![];
-            for repo in for_change {
-                let branches = self.fetch_repo_branches(repo).await?;
-
+            for (repo, branches) in for_change.into_iter().zip(repos_branches) {
                 let current_idx = branches
                     .iter()
                     .position(|b| b.name == repo.main_branch.name);
@@ -143,9 +352,15 @@ impl Wizard {
                 .items(&branches)
                 .default(default_idx)
                 .interact()?;
+                let rename_to = self
+                    .default_branch_name
+                    .as_ref()
+                    .filter(|name| **name != selected_branch.name)
+                    .cloned();
                 let action = Action::SetRepositoryDefaultBranch {
-                    repository_name: repo.full_name.clone(),
+                    repository_name: self.github_full_name(&repo.full_name),
                     branch: selected_branch.name.clone(),
+                    rename_to,
                 };
                 actions.push(action);
             }
@@ -156,24 +371,280 @@ impl Wizard {
         }
     }
 
-    async fn fetch_repo_branches(&self, repo: &Repository) -> anyhow::Result<Vec<Branch>> {
+    /// Sets the Bitbucket default branches on GitHub, skipping repositories which don't get
+    /// the branch pushed (not mirrored, created empty or with the branch not selected).
+    fn source_default_branches(
+        &self,
+        repositories: &[Repository],
+        actions: &[Action],
+    ) -> Vec<Action> {
+        let pushed = actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::MigrateRepositories { repositories } => Some(repositories),
+                _ => None,
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut skipped = vec![];
+        let mut branch_actions = vec![];
+        for repo in repositories {
+            let branch = &repo.main_branch.name;
+            let is_pushed = pushed.iter().any(|r| {
+                r.full_name == repo.full_name
+                    && r.branches
+                        .as_ref()
+                        .is_none_or(|branches| branches.contains(branch))
+            });
+            if !is_pushed {
+                skipped.push(repo.full_name.as_str());
+                continue;
+            }
+
+            let rename_to = self
+                .default_branch_name
+                .as_ref()
+                .filter(|name| *name != branch)
+                .cloned();
+            branch_actions.push(Action::SetRepositoryDefaultBranch {
+                repository_name: self.github_full_name(&repo.full_name),
+                branch: branch.clone(),
+                rename_to,
+            });
+        }
+
+        if !skipped.is_empty() {
+            println!(
+                "Keeping default branches from Bitbucket, except for repositories without the branch pushed:\n{}",
+                skipped
+                    .iter()
+                    .map(|n| format!("  - {}", n))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+
+        branch_actions
+    }
+
+    /// Renames the repositories in GitHub with a regex replace rule applied to their names,
+    /// e.g. `_` replaced with `-`.
+    fn ask_rename_repositories(
+        &self,
+        repositories: &[Repository],
+    ) -> anyhow::Result<Option<Vec<Action>>> {
+        let rename = Confirm::with_prompt("Do you want to rename repositories in GitHub?")
+            .default(false)
+            .interact()?;
+        if !rename {
+            return Ok(None);
+        }
+
+        let pattern = Input::with_prompt("Pattern to replace in the names (regular expression)")
+            .validate_with(|input| Regex::new(input).err().map(|e| e.to_string()))
+            .interact()?;
+        let pattern = Regex::new(&pattern)?;
+        let replacement = Input::with_prompt("Replacement (use $1 for capture groups)")
+            .allow_empty(true)
+            .interact()?;
+
+        let actions = repositories
+            .iter()
+            .filter_map(|repo| {
+                let name = repo.full_name.rsplit('/').next().unwrap_or(&repo.full_name);
+                let new_name = pattern.replace_all(name, replacement.as_str());
+                (new_name != name && !new_name.is_empty()).then(|| Action::RenameRepository {
+                    from_name: self.github_full_name(&repo.full_name),
+                    to_name: new_name.into_owned(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if actions.is_empty() {
+            println!("The rule doesn't change names of any repository, skipping renaming...");
+            return Ok(None);
+        }
+
+        println!(
+            "Repositories to rename:\n{}",
+            actions
+                .iter()
+                .map(|action| format!("  - {}", action.describe()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        Ok(Some(actions))
+    }
+
+    /// Teams with write (or higher) access to the repositories become their code owners,
+    /// as GitHub ignores code owners without write access.
+    fn ask_create_codeowners(
+        repositories_names: &[String],
+        actions: &[Action],
+    ) -> anyhow::Result<Option<Vec<Action>>> {
+        let team_slugs = actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::AssignRepositoriesToTeam {
+                    team_slug,
+                    permission,
+                    ..
+                }
+                | Action::AddRepositoriesToExistingTeam {
+                    team_slug,
+                    permission,
+                    ..
+                } if *permission >= TeamRepositoryPermission::Push => Some(team_slug.clone()),
+                _ => None,
+            })
+            .fold(Vec::new(), |mut slugs, slug| {
+                if !slugs.contains(&slug) {
+                    slugs.push(slug);
+                }
+                slugs
+            });
+
+        if team_slugs.is_empty() {
+            return Ok(None);
+        }
+
+        let create_codeowners = Confirm::with_prompt(format!(
+            "Do you want to create CODEOWNERS file with teams {} as owners?",
+            team_slugs.join(", ")
+        ))
+        .interact()?;
+
+        if create_codeowners {
+            let actions = repositories_names
+                .iter()
+                .map(|repository_name| Action::CreateCodeowners {
+                    repository_name: repository_name.clone(),
+                    team_slugs: team_slugs.clone(),
+                })
+                .collect();
+            Ok(Some(actions))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Fetches branches of the repositories concurrently, keeping the order of repositories
+    async fn fetch_repos_branches(
+        &self,
+        repositories: &[&Repository],
+    ) -> anyhow::Result<Vec<Vec<Branch>>> {
+        const CONCURRENT_REQUESTS: usize = 5;
+
         let spinner = spinner::create_spinner(format!(
-            "Fetching branches for '{}' repository...",
-            repo.full_name
+            "Fetching branches for {} repositories...",
+            repositories.len()
         ));
-        let branches = self
-            .bitbucket
-            .get_repository_branches(&repo.full_name)
+        let branches = futures::stream::iter(repositories)
+            .map(|repo| async move {
+                self.bitbucket
+                    .get_repository_branches(&repo.full_name)
+                    .await
+                    .with_context(|| {
+                        format!("Cannot fetch branches of '{}' repository", repo.full_name)
+                    })
+            })
+            .buffered(CONCURRENT_REQUESTS)
+            .try_collect::<Vec<_>>()
             .await?;
         spinner.finish_with_message(format!(
-            "Fetched {} branches for '{}' repository!",
-            branches.len(),
-            repo.full_name
+            "Fetched branches for {} repositories!",
+            repositories.len()
         ));
 
         Ok(branches)
     }
 
+    /// Turns the teams from the teams file into actions, without any prompts.
+    ///
+    /// Teams which already exist on GitHub are updated instead of being created.
+    /// Fails when some of the members don't belong to the organization.
+    async fn teams_file_actions(
+        &self,
+        teams_file: &TeamsFile,
+        existing_teams: &[Team],
+    ) -> anyhow::Result<Vec<Action>> {
+        let people = self.github.get_org_members().await?;
+        let mut unknown = teams_file
+            .teams()
+            .flat_map(|(_, team)| &team.members)
+            .filter(|login| !people.iter().any(|p| p.login.eq_ignore_ascii_case(login)))
+            .map(|login| login.as_str())
+            .collect::<Vec<_>>();
+        unknown.sort_unstable();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            bail!(
+                "Members of the teams file not found in {} organization: {}",
+                self.github.organization_name(),
+                unknown.join(", ")
+            );
+        }
+
+        let mut actions = vec![];
+        for (name, team) in teams_file.teams() {
+            let repositories = team
+                .repositories
+                .iter()
+                .map(|r| {
+                    if r.contains('/') {
+                        r.clone()
+                    } else {
+                        format!("{}/{}", self.github.organization_name(), r)
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let existing = existing_teams
+                .iter()
+                .find(|t| t.name.eq_ignore_ascii_case(name));
+            let team_slug = match existing {
+                Some(existing) => existing.slug.clone(),
+                None => {
+                    actions.push(Action::CreateTeam {
+                        name: name.clone(),
+                        repositories: repositories.clone(),
+                        description: team.description.clone(),
+                        parent_team_slug: None,
+                    });
+                    Wizard::team_slug(name)
+                }
+            };
+            if !team.members.is_empty() {
+                actions.push(Action::AddMembersToTeam {
+                    team_name: name.clone(),
+                    team_slug: team_slug.clone(),
+                    members: team.members.clone(),
+                });
+            }
+            if repositories.is_empty() {
+                continue;
+            }
+            actions.push(match existing {
+                Some(_) => Action::AddRepositoriesToExistingTeam {
+                    team_name: name.clone(),
+                    team_slug,
+                    permission: team.permission.clone(),
+                    repositories,
+                },
+                None => Action::AssignRepositoriesToTeam {
+                    team_name: name.clone(),
+                    team_slug,
+                    permission: team.permission.clone(),
+                    repositories,
+                },
+            });
+        }
+
+        Ok(actions)
+    }
+
     fn ask_additional_teams(
         &self,
         repositories_names: &[String],
@@ -187,14 +658,18 @@ impl Wizard {
                 .items(teams)
                 .interact()?;
 
-            let permission_actions = teams
-                .iter()
-                .flat_map(|team| {
-                    self.select_permissions_action(&team.name, Some(&team.slug), repositories_names)
-                })
-                .collect();
+            let mut actions = vec![];
+            for team in teams {
+                let permission = Self::select_permission(&format!("'{}' team", team.name))?;
+                actions.push(Action::AddRepositoriesToExistingTeam {
+                    team_name: team.name.clone(),
+                    team_slug: team.slug.clone(),
+                    permission,
+                    repositories: repositories_names.to_vec(),
+                });
+            }
 
-            Ok(Some(permission_actions))
+            Ok(Some(actions))
         } else {
             Ok(None)
         }
@@ -205,16 +680,17 @@ impl Wizard {
         project_name: &str,
         repositories_names: &[String],
         existing_teams: &[Team],
+        suggested_members: &[String],
     ) -> anyhow::Result<Option<Vec<Action>>> {
         let create_team_confirm =
             Confirm::with_prompt("Do you want to create a new team for selected repositories?")
                 .interact()?;
         let create_team_actions = if create_team_confirm {
-            let existing_teams = existing_teams.to_vec();
+            let teams = existing_teams.to_vec();
             let team_name = Input::with_prompt("Team name")
                 .initial_text(project_name)
                 .validate_with(move |input| {
-                    if existing_teams.iter().any(|t| t.name == *input) {
+                    if teams.iter().any(|t| t.name == *input) {
                         Some(format!("Team with '{}' name already exist", input))
                     } else {
                         None
@@ -222,14 +698,28 @@ impl Wizard {
                 })
                 .interact()?;
 
+            let description = Input::with_prompt("Team description (empty for none)")
+                .allow_empty(true)
+                .interact()?;
+            let description = Some(description.trim().to_string()).filter(|d| !d.is_empty());
+
             let team_slug = Wizard::team_slug(&team_name);
             let people = self.github.get_org_members().await?;
+            let defaults = people
+                .iter()
+                .map(|p| {
+                    suggested_members
+                        .iter()
+                        .any(|m| m.eq_ignore_ascii_case(&p.login))
+                })
+                .collect::<Vec<_>>();
 
             let members = MultiSelect::with_prompt(format!(
                 "Select members for the '{}' team\n(include yourself if you should be part of the team)",
                 &team_name
             ))
                 .items(&people)
+                .defaults(&defaults)
                 .interact()?;
 
             let members: Vec<String> = members
@@ -242,6 +732,8 @@ impl Wizard {
             let create_team = Action::CreateTeam {
                 name: team_name.clone(),
                 repositories: repositories_names.to_vec(),
+                description,
+                parent_team_slug: Self::ask_parent_team(&team_name, existing_teams)?,
             };
             let add_members_to_team = Action::AddMembersToTeam {
                 team_name,
@@ -256,6 +748,84 @@ impl Wizard {
         Ok(create_team_actions)
     }
 
+    /// Asks for the existing team to nest the new one under, `None` when it shouldn't be nested.
+    fn ask_parent_team(team_name: &str, existing_teams: &[Team]) -> anyhow::Result<Option<String>> {
+        if existing_teams.is_empty() {
+            return Ok(None);
+        }
+
+        let nest = Confirm::with_prompt(format!(
+            "Do you want to nest the '{}' team under an existing one?",
+            team_name
+        ))
+        .default(false)
+        .interact()?;
+        if !nest {
+            return Ok(None);
+        }
+
+        let parent =
+            FuzzySelect::with_prompt(format!("Select parent team of the '{}' team", team_name))
+                .items(existing_teams)
+                .default(0)
+                .interact()?;
+
+        Ok(Some(parent.slug.clone()))
+    }
+
+    async fn ask_add_collaborators(
+        &self,
+        repositories_names: &[String],
+        suggested_members: &[String],
+    ) -> anyhow::Result<Option<Vec<Action>>> {
+        let add_collaborators = Confirm::with_prompt(
+            "Do you want to add individual collaborators to these repositories?",
+        )
+        .default(false)
+        .interact()?;
+
+        if !add_collaborators {
+            return Ok(None);
+        }
+
+        let people = self.github.get_org_members().await?;
+        let defaults = people
+            .iter()
+            .map(|p| {
+                suggested_members
+                    .iter()
+                    .any(|m| m.eq_ignore_ascii_case(&p.login))
+            })
+            .collect::<Vec<_>>();
+        let selected = MultiSelect::with_prompt("Select collaborators")
+            .items(&people)
+            .defaults(&defaults)
+            .interact()?;
+
+        if selected.is_empty() {
+            println!("No collaborators selected, skipping...");
+            return Ok(None);
+        }
+
+        let mut collaborators = vec![];
+        for member in selected {
+            collaborators.push(Collaborator {
+                login: member.login.clone(),
+                permission: Self::select_permission(&format!("'{}' user", member.login))?,
+            });
+        }
+
+        let actions = repositories_names
+            .iter()
+            .map(|repository_name| Action::AddCollaborators {
+                repository_name: repository_name.clone(),
+                collaborators: collaborators.clone(),
+            })
+            .collect();
+
+        Ok(Some(actions))
+    }
+
     async fn fetch_github_teams(&self) -> anyhow::Result<Vec<Team>> {
         let spinner = spinner::create_spinner("Fetching teams...");
         let teams = self.github.get_teams().await?;
@@ -264,21 +834,273 @@ impl Wizard {
         Ok(teams)
     }
 
-    fn ask_clone_repos(repositories: &[BitbucketRepository]) -> anyhow::Result<Option<Action>> {
-        let migrate_repos = Confirm::with_prompt(
+    /// Asks which repositories should be mirrored from Bitbucket and which should be created
+    /// empty in GitHub (to be seeded manually later).
+    async fn ask_clone_repos(
+        &self,
+        repositories: &[BitbucketRepository],
+    ) -> anyhow::Result<Vec<Action>> {
+        let options = [
+            "Mirror all repositories",
+            "Create empty repositories without mirroring",
+            "Choose repositories to mirror (others will be created empty)",
+            "Skip creating repositories",
+        ];
+        let choice = Select::with_prompt(
             "Do you want to mirror selected repositories from Bitbucket to GitHub?",
         )
+        .items(&options)
+        .default(0)
+        .interact_idx_opt()?
+        .ok_or_else(|| anyhow!("Wizard canceled"))?;
+
+        let (mirrored, created): (Vec<_>, Vec<_>) = match choice {
+            0 => (repositories.iter().collect(), vec![]),
+            1 => (vec![], repositories.iter().collect()),
+            2 => {
+                let selected = MultiSelect::with_prompt("Select repositories to mirror")
+                    .items(repositories)
+                    .defaults(&vec![true; repositories.len()])
+                    .interact_idx()?;
+                let (mirrored, created): (Vec<_>, Vec<_>) = repositories
+                    .iter()
+                    .enumerate()
+                    .partition(|(idx, _)| selected.contains(idx));
+                (
+                    mirrored.into_iter().map(|(_, r)| r).collect(),
+                    created.into_iter().map(|(_, r)| r).collect(),
+                )
+            }
+            3 => return Ok(vec![]),
+            _ => unreachable!(),
+        };
+
+        let visibility = self.ask_visibility().await?;
+        let with_visibility = |mut repository: MigrationRepository| {
+            repository.visibility = visibility.clone();
+            repository
+        };
+
+        let mut actions = vec![];
+        if !created.is_empty() {
+            let init = Self::ask_init()?;
+            actions.push(Action::CreateRepositories {
+                repositories: created
+                    .into_iter()
+                    .map(|r| with_visibility(r.clone().into()))
+                    .collect(),
+                init,
+            });
+        }
+        if !mirrored.is_empty() {
+            actions.push(Action::MigrateRepositories {
+                repositories: self
+                    .ask_branches_to_push(&mirrored)
+                    .await?
+                    .into_iter()
+                    .map(with_visibility)
+                    .collect(),
+            });
+        }
+        Ok(actions)
+    }
+
+    /// Asks for visibility of the created repositories, only when the organization allows `internal` ones.
+    ///
+    /// Returns `None` for (default) private repositories.
+    async fn ask_visibility(&self) -> anyhow::Result<Option<RepositoryVisibility>> {
+        if !self.github.supports_internal_repositories().await? {
+            return Ok(None);
+        }
+
+        let visibilities = [
+            RepositoryVisibility::Private,
+            RepositoryVisibility::Internal,
+        ];
+        let visibility = Select::with_prompt("Select visibility of the created repositories")
+            .items(&visibilities)
+            .default(0)
+            .interact()?;
+
+        Ok(match visibility {
+            RepositoryVisibility::Private => None,
+            visibility => Some(visibility.clone()),
+        })
+    }
+
+    /// Asks for the initial commit of the repositories created without mirroring.
+    fn ask_init() -> anyhow::Result<Option<RepositoryInit>> {
+        let auto_init = Confirm::with_prompt(
+            "Do you want to initialize the empty repositories with a README commit?",
+        )
+        .default(false)
         .interact()?;
-        if migrate_repos {
-            let migrate_action = Action::MigrateRepositories {
-                repositories: repositories.iter().map(|r| r.clone().into()).collect(),
-            };
-            Ok(Some(migrate_action))
+        if !auto_init {
+            return Ok(None);
+        }
+
+        let template = |prompt: &str| -> anyhow::Result<Option<String>> {
+            let template = Input::with_prompt(prompt).allow_empty(true).interact()?;
+            Ok(Some(template.trim().to_string()).filter(|t| !t.is_empty()))
+        };
+        Ok(Some(RepositoryInit {
+            auto_init,
+            gitignore_template: template(".gitignore template (e.g. Rust, empty for none)")?,
+            license_template: template("License template (e.g. mit, empty for none)")?,
+        }))
+    }
+
+    /// Allows pushing only selected branches (and tags) of the repositories, instead of mirroring all refs.
+    async fn ask_branches_to_push(
+        &self,
+        repositories: &[&BitbucketRepository],
+    ) -> anyhow::Result<Vec<MigrationRepository>> {
+        let mut mirrored = repositories
+            .iter()
+            .map(|r| (*r).clone().into())
+            .collect::<Vec<MigrationRepository>>();
+
+        let select_branches = Confirm::with_prompt(
+            "Do you want to push only selected branches of some repositories (instead of mirroring)?",
+        )
+        .default(false)
+        .interact()?;
+        if !select_branches {
+            return Ok(mirrored);
+        }
+
+        let selected = MultiSelect::with_prompt("Select repositories to push selected branches of")
+            .items(repositories)
+            .interact_idx()?;
+        let for_selection = selected
+            .iter()
+            .map(|&idx| repositories[idx])
+            .collect::<Vec<_>>();
+        let repos_branches = self.fetch_repos_branches(&for_selection).await?;
+
+        for (idx, branches) in selected.into_iter().zip(repos_branches) {
+            let repo = repositories[idx];
+            let defaults = branches
+                .iter()
+                .map(|b| b.name == repo.main_branch.name)
+                .collect::<Vec<_>>();
+            let to_push = MultiSelect::with_prompt(format!(
+                "Select branches of '{}' repository to push",
+                repo.full_name
+            ))
+            .items(&branches)
+            .defaults(&defaults)
+            .interact()?;
+            let to_push = to_push.into_iter().map(|b| b.name.clone()).collect();
+            mirrored[idx].branches = Self::branches_to_push(&repo.full_name, to_push);
+        }
+
+        Ok(mirrored)
+    }
+
+    /// Pushing none of the branches would leave only tags in GitHub, so such repositories are mirrored
+    fn branches_to_push(repository_name: &str, branches: Vec<String>) -> Option<Vec<String>> {
+        if branches.is_empty() {
+            println!(
+                "No branches of '{}' repository selected, mirroring all of them...",
+                repository_name
+            );
+            None
         } else {
-            Ok(None)
+            Some(branches)
         }
     }
 
+    fn ask_repository_settings(
+        repositories_names: &[String],
+    ) -> anyhow::Result<Option<Vec<Action>>> {
+        let change_settings =
+            Confirm::with_prompt("Do you want to change merge settings of these repositories?")
+                .interact()?;
+
+        if !change_settings {
+            return Ok(None);
+        }
+
+        let settings = RepositorySettings {
+            allow_squash_merge: Confirm::with_prompt("Allow squash merging?")
+                .default(true)
+                .interact()?,
+            allow_merge_commit: Confirm::with_prompt("Allow merge commits?")
+                .default(true)
+                .interact()?,
+            allow_rebase_merge: Confirm::with_prompt("Allow rebase merging?")
+                .default(true)
+                .interact()?,
+            delete_branch_on_merge: Confirm::with_prompt(
+                "Automatically delete head branches after merging?",
+            )
+            .default(true)
+            .interact()?,
+        };
+
+        let actions = repositories_names
+            .iter()
+            .map(|repository_name| Action::SetRepositorySettings {
+                repository_name: repository_name.clone(),
+                settings: settings.clone(),
+            })
+            .collect();
+
+        Ok(Some(actions))
+    }
+
+    async fn ask_migrate_deploy_keys(
+        &self,
+        repositories: &[BitbucketRepository],
+    ) -> anyhow::Result<Option<Vec<Action>>> {
+        let spinner = spinner::create_spinner("Fetching deploy keys from Bitbucket...");
+        let mut repositories_keys = vec![];
+        for repository in repositories {
+            let keys = self
+                .bitbucket
+                .get_deploy_keys(&repository.full_name)
+                .await?;
+            if !keys.is_empty() {
+                repositories_keys.push((repository, keys));
+            }
+        }
+        spinner.finish_with_message(format!(
+            "Found deploy keys in {} repositories",
+            repositories_keys.len()
+        ));
+
+        if repositories_keys.is_empty() {
+            return Ok(None);
+        }
+
+        let migrate_keys = Confirm::with_prompt(format!(
+            "Do you want to migrate deploy keys of {} repositories?\n(Only public keys are migrated, as read-only keys)",
+            repositories_keys.len()
+        ))
+        .interact()?;
+
+        if !migrate_keys {
+            return Ok(None);
+        }
+
+        let actions = repositories_keys
+            .into_iter()
+            .map(|(repository, keys)| Action::MigrateDeployKeys {
+                repository_name: self.github_full_name(&repository.full_name),
+                keys: keys
+                    .into_iter()
+                    .map(|k| DeployKey {
+                        title: k.label,
+                        key: k.key,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Ok(Some(actions))
+    }
+
     fn select_repositories_to_continue(
         repositories: &[BitbucketRepository],
         already_migrated: &[&String],
@@ -290,18 +1112,35 @@ impl Wizard {
                 .collect::<Vec<_>>()
                 .join(", ");
             let msg = format!("The following repositories already exist in GitHub: {}\nDo you want to update them?", intersection_names);
-            let options = ["Update existing repositories", "Skip existing repositories"];
+            let options = [
+                "Update existing repositories",
+                "Skip existing repositories",
+                "Choose repositories to continue with",
+            ];
             let overwrite = Select::with_prompt(msg)
                 .items(&options)
                 .default(1)
-                .interact_idx()?;
-            match overwrite {
+                .interact_idx_opt()?;
+            match overwrite.ok_or_else(|| anyhow!("Wizard canceled"))? {
                 0 => repositories.to_vec(),
                 1 => repositories
                     .iter()
                     .filter(|r| !already_migrated.contains(&&r.full_name))
                     .cloned()
                     .collect::<Vec<_>>(),
+                2 => {
+                    let not_migrated = repositories
+                        .iter()
+                        .map(|r| !already_migrated.contains(&&r.full_name))
+                        .collect::<Vec<_>>();
+                    MultiSelect::with_prompt("Select repositories to continue with")
+                        .items(repositories)
+                        .defaults(&not_migrated)
+                        .interact()?
+                        .into_iter()
+                        .cloned()
+                        .collect::<Vec<_>>()
+                }
                 _ => unreachable!(),
             }
         } else {
@@ -311,43 +1150,64 @@ impl Wizard {
         Ok(repositories)
     }
 
-    fn already_migrated_repo_names<'a>(
+    /// Repositories which the user chose not to continue with, together with the reason.
+    fn skipped_repositories(
+        repositories: &[BitbucketRepository],
+        selected: &[BitbucketRepository],
+        already_migrated: &[&String],
+    ) -> Vec<SkippedRepository> {
+        repositories
+            .iter()
+            .filter(|r| !selected.iter().any(|s| s.full_name == r.full_name))
+            .map(|r| SkippedRepository {
+                name: r.full_name.clone(),
+                reason: if already_migrated.contains(&&r.full_name) {
+                    "already exists in GitHub".to_string()
+                } else {
+                    "not selected to continue with".to_string()
+                },
+            })
+            .collect()
+    }
+
+    /// Checks which of the repositories already exist on GitHub, one request per repository,
+    /// so the (possibly huge) list of organization repositories doesn't have to be fetched.
+    async fn already_migrated_repo_names<'a>(
+        &self,
         bb_repositories: &'a [BitbucketRepository],
-        gh_repositories: &'a [GitHubRepository],
-    ) -> Vec<&'a String> {
+    ) -> anyhow::Result<Vec<&'a String>> {
+        const CONCURRENT_REQUESTS: usize = 10;
+
         let spinner = spinner::create_spinner("Checking for existing repositories in GitHub...");
-        let selected_repo_names = bb_repositories
-            .iter()
-            .map(|r| &r.full_name)
-            .collect::<HashSet<_>>();
+        let existing = futures::stream::iter(bb_repositories)
+            .map(|repo| async move {
+                let name = repo.full_name.rsplit('/').next().unwrap_or(&repo.full_name);
+                let exists = self
+                    .github
+                    .get_repository(name)
+                    .await
+                    .with_context(|| format!("Cannot check whether '{}' repository exists", name))?
+                    .is_some();
+                Ok::<_, anyhow::Error>((&repo.full_name, exists))
+            })
+            .buffer_unordered(CONCURRENT_REQUESTS)
+            .try_filter_map(|(name, exists)| async move { Ok(exists.then_some(name)) })
+            .try_collect::<HashSet<_>>()
+            .await?;
 
-        let existing_repo_names = gh_repositories
+        // Keep the order of the selected repositories
+        let intersection = bb_repositories
             .iter()
             .map(|r| &r.full_name)
-            .collect::<HashSet<_>>();
-
-        let intersection = selected_repo_names
-            .intersection(&existing_repo_names)
-            .cloned()
+            .filter(|name| existing.contains(name))
             .collect::<Vec<_>>();
         spinner.finish_with_message(format!(
             "{} of the {} selected repositories already exist on GitHub",
             intersection.len(),
-            selected_repo_names.len(),
-        ));
-
-        intersection
-    }
-
-    async fn fetch_github_repositories(&self) -> anyhow::Result<Vec<GitHubRepository>> {
-        let spinner = spinner::create_spinner("Fetching existing repositories from GitHub...");
-        let github_repositories = self.github.get_repositories().await?;
-        spinner.finish_with_message(format!(
-            "Fetched {} existing repositories from GitHub!",
-            github_repositories.len()
+            bb_repositories.len(),
         ));
 
-        Ok(github_repositories)
+        Ok(intersection)
     }
 
     fn select_permissions_action(
@@ -356,6 +1216,59 @@ impl Wizard {
         team_slug: Option<&str>,
         repositories_names: &[String],
     ) -> Result<Action, anyhow::Error> {
+        let permission = Self::select_permission(&format!("'{}' team", team_name))?;
+
+        Ok(Action::AssignRepositoriesToTeam {
+            team_name: team_name.to_string(),
+            team_slug: team_slug.map_or(Wizard::team_slug(team_name), |s| s.to_owned()),
+            permission,
+            repositories: repositories_names.to_vec(),
+        })
+    }
+
+    /// Returns GitHub logins of the Bitbucket committers of the repositories, found in the user map.
+    ///
+    /// Committers missing in the user map are reported and skipped.
+    async fn suggested_members(
+        &self,
+        repositories: &[BitbucketRepository],
+    ) -> anyhow::Result<Vec<String>> {
+        let user_map = match &self.user_map {
+            Some(user_map) => user_map,
+            None => return Ok(vec![]),
+        };
+
+        let spinner = spinner::create_spinner("Fetching committers of selected repositories...");
+        let mut committers = HashSet::new();
+        for repository in repositories {
+            let users = self
+                .bitbucket
+                .get_repository_committers(&repository.full_name)
+                .await?;
+            committers.extend(users.into_iter().map(|u| u.nickname));
+        }
+        spinner.finish_with_message(format!("Found {} committers", committers.len()));
+
+        let mut committers = committers.into_iter().collect::<Vec<_>>();
+        committers.sort();
+        let (mapped, unmapped) = user_map.translate(committers.iter().map(|c| c.as_str()));
+        if !unmapped.is_empty() {
+            println!(
+                "Skipping {} Bitbucket users missing in the user map:\n{}",
+                unmapped.len(),
+                unmapped
+                    .iter()
+                    .map(|u| format!("  - {}", u))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+
+        Ok(mapped)
+    }
+
+    /// Asks for the permission to the repositories, `grantee` being e.g. `'developers' team`
+    fn select_permission(grantee: &str) -> anyhow::Result<TeamRepositoryPermission> {
         let permissions = vec![
             TeamRepositoryPermission::Pull,
             TeamRepositoryPermission::Triage,
@@ -363,20 +1276,15 @@ impl Wizard {
             TeamRepositoryPermission::Maintain,
         ];
         let permission = Select::with_prompt(format!(
-            "Select permission to the repositories for '{}' team",
-            &team_name
+            "Select permission to the repositories for {}",
+            grantee
         ))
         .items(&permissions)
         .default(2)
         .interact()?
         .clone();
 
-        Ok(Action::AssignRepositoriesToTeam {
-            team_name: team_name.to_string(),
-            team_slug: team_slug.map_or(Wizard::team_slug(team_name), |s| s.to_owned()),
-            permission,
-            repositories: repositories_names.to_vec(),
-        })
+        Ok(permission)
     }
 
     async fn select_repositories(
@@ -385,15 +1293,57 @@ impl Wizard {
     ) -> Result<Vec<BitbucketRepository>, anyhow::Error> {
         let spinner =
             spinner::create_spinner(format!("Fetching repositories from {} project", project));
-        let repositories = self
+        let mut repositories = self
             .bitbucket
             .get_project_repositories(project.get_key())
             .await?;
-        spinner.finish_with_message(format!(
-            "Fetched {} repositories from {} project!",
-            repositories.len(),
-            project
-        ));
+        repositories.sort_by_key(|r| r.full_name.to_lowercase());
+
+        match &self.since {
+            Some(since) => {
+                let fetched = repositories.len();
+                // Both dates are in ISO 8601 format, so they can be compared as strings
+                repositories.retain(|r| {
+                    r.updated_on
+                        .as_ref()
+                        .is_none_or(|updated_on| updated_on.as_str() >= since.as_str())
+                });
+                spinner.finish_with_message(format!(
+                    "Fetched {} repositories from {} project! (skipped {} not updated since {})",
+                    repositories.len(),
+                    project,
+                    fetched - repositories.len(),
+                    since
+                ));
+            }
+            None => spinner.finish_with_message(format!(
+                "Fetched {} repositories from {} project!",
+                repositories.len(),
+                project
+            )),
+        }
+
+        if self.filter.is_some() || self.exclude.is_some() {
+            let fetched = repositories.len();
+            repositories.retain(|r| {
+                let name = r.full_name.rsplit('/').next().unwrap_or(&r.full_name);
+                self.filter.as_ref().is_none_or(|f| f.is_match(name))
+                    && !self.exclude.as_ref().is_some_and(|e| e.is_match(name))
+            });
+            println!(
+                "{} of {} repositories match the filters",
+                repositories.len(),
+                fetched
+            );
+        }
+
+        if self.all_repositories {
+            if repositories.is_empty() {
+                return Err(anyhow!("No repositories found in {} project", project));
+            }
+            return Ok(repositories);
+        }
+
         let repositories =
             MultiSelect::with_prompt(format!("Select repositories from {} project", project))
                 .items(&repositories)
@@ -407,10 +1357,114 @@ impl Wizard {
         Ok(repositories)
     }
 
+    async fn repositories_from_file(
+        &self,
+        path: &Path,
+    ) -> Result<Vec<BitbucketRepository>, anyhow::Error> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read repositories file {}", path.display()))?;
+        let names = contents
+            .split(['\n', ','])
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            return Err(anyhow!("No repositories listed in {}", path.display()));
+        }
+
+        self.fetch_repositories(&names).await
+    }
+
+    /// Returns the project name and repositories chosen in the wizard which generated the migration file
+    async fn repositories_from_migration(
+        &self,
+        path: &Path,
+    ) -> Result<(String, Vec<BitbucketRepository>), anyhow::Error> {
+        let migration: Migration = migration_file::read(path)
+            .with_context(|| format!("Error when parsing {} file", path.display()))?;
+        let selection = migration.selection().ok_or_else(|| {
+            anyhow!(
+                "Migration file {} doesn't contain the selected repositories",
+                path.display()
+            )
+        })?;
+        let names = selection
+            .repositories
+            .iter()
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+
+        let repositories = self.fetch_repositories(&names).await?;
+
+        Ok((selection.project.clone(), repositories))
+    }
+
+    /// Fetches the repositories by their full names, asking whether to continue when some are missing
+    async fn fetch_repositories(
+        &self,
+        names: &[&str],
+    ) -> Result<Vec<BitbucketRepository>, anyhow::Error> {
+        let spinner = spinner::create_spinner(format!(
+            "Fetching {} repositories from Bitbucket...",
+            names.len()
+        ));
+        let mut repositories = vec![];
+        let mut not_found = vec![];
+        for &name in names {
+            match self.bitbucket.get_repository(name).await? {
+                Some(repository) => repositories.push(repository),
+                None => not_found.push(name),
+            }
+        }
+        spinner.finish_with_message(format!(
+            "Fetched {} repositories from Bitbucket!",
+            repositories.len()
+        ));
+
+        if !not_found.is_empty() {
+            println!(
+                "The following repositories were not found in Bitbucket:\n{}",
+                not_found
+                    .iter()
+                    .map(|n| format!("  - {}", n))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+            let proceed = Confirm::with_prompt("Do you want to continue without them?")
+                .default(false)
+                .interact()?;
+            if !proceed {
+                return Err(anyhow!("Wizard canceled"));
+            }
+        }
+
+        if repositories.is_empty() {
+            return Err(anyhow!("At least one repository must be selected"));
+        }
+
+        Ok(repositories)
+    }
+
     async fn select_project(&self) -> Result<bitbucket::Project, anyhow::Error> {
         let spinner = spinner::create_spinner("Fetching projects from Bitbucket...");
         let projects = self.bitbucket.get_projects().await?;
         spinner.finish_with_message("Fetched!");
+
+        if let Some(key) = &self.project_key {
+            return projects
+                .iter()
+                .find(|p| p.get_key().eq_ignore_ascii_case(key))
+                .cloned()
+                .ok_or_else(|| {
+                    let keys = projects.iter().map(|p| p.get_key()).collect::<Vec<_>>();
+                    anyhow!(
+                        "Project '{}' not found in Bitbucket, available projects: {}",
+                        key,
+                        keys.join(", ")
+                    )
+                });
+        }
+
         let project = FuzzySelect::with_prompt("Select project")
             .items(&projects)
             .default(0)
@@ -430,17 +1484,266 @@ impl Wizard {
                 return Err(anyhow!("Migration file already exists"));
             }
         }
-        let mut file = File::create(&self.output_path)?;
-
-        serde_json::to_writer(&mut file, migration)?;
+        migration_file::write(&self.output_path, migration)?;
 
         Ok(())
     }
 
+    /// Mirrors GitHub's slugification - runs of characters other than ASCII letters and digits
+    /// are replaced with a single `-`, without leading and trailing dashes.
     fn team_slug(team_name: &str) -> String {
-        let regex = regex::Regex::new(r"[^a-zA-Z0-9\-]").unwrap();
-        regex
-            .replace_all(&team_name.to_lowercase(), "-")
-            .to_string()
+        team_name
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn wizard(github_api_url: &str) -> Wizard {
+        Wizard::new(
+            PathBuf::from("migration.json"),
+            "0.0.0",
+            BitbucketConfig {
+                username: "user".to_string(),
+                password: "password".to_string(),
+                access_token: None,
+                workspace_name: "acme".to_string(),
+                api_url: "http://localhost".to_string(),
+            },
+            GitHubConfig {
+                username: "user".to_string(),
+                password: "token".to_string(),
+                organization_name: "org".to_string(),
+                user_account: false,
+                api_url: github_api_url.to_string(),
+            },
+        )
+    }
+
+    fn bitbucket_repository(full_name: &str) -> BitbucketRepository {
+        serde_json::from_value(json!({
+            "links": { "clone": [
+                { "name": "ssh", "href": format!("git@bitbucket.org:{}.git", full_name) },
+            ] },
+            "full_name": full_name,
+            "name": full_name.rsplit('/').next().unwrap(),
+            "mainbranch": { "name": "master" },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn actions_use_github_organization_instead_of_bitbucket_workspace() {
+        let wizard = wizard("http://localhost");
+        let repository = bitbucket_repository("acme/api");
+        let actions = vec![Action::MigrateRepositories {
+            repositories: vec![MigrationRepository::from(repository.clone())],
+        }];
+
+        let branch_actions = wizard.source_default_branches(&[repository], &actions);
+
+        assert_eq!(
+            branch_actions,
+            vec![Action::SetRepositoryDefaultBranch {
+                repository_name: "org/api".to_string(),
+                branch: "master".to_string(),
+                rename_to: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn empty_branches_selection_mirrors_repository() {
+        assert_eq!(Wizard::branches_to_push("acme/api", vec![]), None);
+        assert_eq!(
+            Wizard::branches_to_push("acme/api", vec!["main".to_string()]),
+            Some(vec!["main".to_string()])
+        );
+    }
+
+    fn teams_file(yaml: &str) -> TeamsFile {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    async fn mount_org_members(server: &MockServer, logins: &[&str]) {
+        let members = logins
+            .iter()
+            .enumerate()
+            .map(|(id, login)| json!({ "login": login, "id": id }))
+            .collect::<Vec<_>>();
+        Mock::given(method("GET"))
+            .and(path("/orgs/org/members"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(members))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn teams_file_rejects_unknown_members() {
+        let server = MockServer::start().await;
+        mount_org_members(&server, &["octocat"]).await;
+        let teams_file = teams_file(
+            "Backend:\n  members: [octocat, hubot]\nMobile:\n  members: [hubot, bender]\n",
+        );
+
+        let err = wizard(&server.uri())
+            .teams_file_actions(&teams_file, &[])
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Members of the teams file not found in org organization: bender, hubot"
+        );
+    }
+
+    #[tokio::test]
+    async fn teams_file_creates_missing_teams_and_updates_existing_ones() {
+        let server = MockServer::start().await;
+        mount_org_members(&server, &["octocat", "hubot"]).await;
+        let teams_file = teams_file(
+            "Backend:\n  members: [octocat]\n  repositories: [api, other/worker]\n\
+             Mobile:\n  description: Mobile developers\n  members: [hubot]\n  repositories: [app]\n  permission: admin\n",
+        );
+        let existing_teams: Vec<Team> = serde_json::from_value(json!([
+            { "name": "Backend", "id": 1, "slug": "backend-team", "privacy": "closed" },
+        ]))
+        .unwrap();
+
+        let actions = wizard(&server.uri())
+            .teams_file_actions(&teams_file, &existing_teams)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            actions,
+            vec![
+                Action::AddMembersToTeam {
+                    team_name: "Backend".to_string(),
+                    team_slug: "backend-team".to_string(),
+                    members: vec!["octocat".to_string()],
+                },
+                Action::AddRepositoriesToExistingTeam {
+                    team_name: "Backend".to_string(),
+                    team_slug: "backend-team".to_string(),
+                    permission: TeamRepositoryPermission::Push,
+                    repositories: vec!["org/api".to_string(), "other/worker".to_string()],
+                },
+                Action::CreateTeam {
+                    name: "Mobile".to_string(),
+                    repositories: vec!["org/app".to_string()],
+                    description: Some("Mobile developers".to_string()),
+                    parent_team_slug: None,
+                },
+                Action::AddMembersToTeam {
+                    team_name: "Mobile".to_string(),
+                    team_slug: "mobile".to_string(),
+                    members: vec!["hubot".to_string()],
+                },
+                Action::AssignRepositoriesToTeam {
+                    team_name: "Mobile".to_string(),
+                    team_slug: "mobile".to_string(),
+                    permission: TeamRepositoryPermission::Admin,
+                    repositories: vec!["org/app".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn teams_file_matches_existing_teams_ignoring_case() {
+        let server = MockServer::start().await;
+        mount_org_members(&server, &[]).await;
+        let teams_file = teams_file("backend:\n  repositories: [api]\n");
+        let existing_teams: Vec<Team> = serde_json::from_value(json!([
+            { "name": "Backend", "id": 1, "slug": "backend", "privacy": "closed" },
+        ]))
+        .unwrap();
+
+        let actions = wizard(&server.uri())
+            .teams_file_actions(&teams_file, &existing_teams)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            actions,
+            vec![Action::AddRepositoriesToExistingTeam {
+                team_name: "backend".to_string(),
+                team_slug: "backend".to_string(),
+                permission: TeamRepositoryPermission::Push,
+                repositories: vec!["org/api".to_string()],
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn teams_file_is_rejected_for_user_account() {
+        let mut wizard = Wizard::new(
+            PathBuf::from("migration.json"),
+            "0.0.0",
+            BitbucketConfig {
+                username: "user".to_string(),
+                password: "password".to_string(),
+                access_token: None,
+                workspace_name: "acme".to_string(),
+                api_url: "http://localhost".to_string(),
+            },
+            GitHubConfig {
+                username: "user".to_string(),
+                password: "token".to_string(),
+                organization_name: "user".to_string(),
+                user_account: true,
+                api_url: "http://localhost".to_string(),
+            },
+        );
+        wizard.teams_file(TeamsFile::default());
+
+        let err = wizard.run().await.unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Teams file cannot be used, as the repositories are migrated to a user account"
+        );
+    }
+
+    #[test]
+    fn team_slug_lowercases_name() {
+        assert_eq!(Wizard::team_slug("Backend"), "backend");
+        assert_eq!(Wizard::team_slug("mobile-team"), "mobile-team");
+    }
+
+    #[test]
+    fn team_slug_replaces_spaces() {
+        assert_eq!(Wizard::team_slug("Mobile Team"), "mobile-team");
+        assert_eq!(Wizard::team_slug("  Mobile   Team  "), "mobile-team");
+    }
+
+    #[test]
+    fn team_slug_collapses_parentheses() {
+        assert_eq!(Wizard::team_slug("Team!! (core)"), "team-core");
+        assert_eq!(Wizard::team_slug("(core)"), "core");
+    }
+
+    #[test]
+    fn team_slug_collapses_ampersands() {
+        assert_eq!(Wizard::team_slug("Design & UX"), "design-ux");
+        assert_eq!(Wizard::team_slug("R&D"), "r-d");
+    }
+
+    #[test]
+    fn team_slug_replaces_unicode() {
+        assert_eq!(Wizard::team_slug("Café Team"), "caf-team");
+        assert_eq!(Wizard::team_slug("Zespół Łódź"), "zesp-d");
+        assert_eq!(Wizard::team_slug("🚀 Rocket"), "rocket");
     }
 }
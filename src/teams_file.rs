@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::github::TeamRepositoryPermission;
+use crate::migration_file;
+
+/// Teams to create (or update, when they already exist) in the GitHub organization.
+///
+/// The file is a JSON or YAML object with names of the teams as keys, e.g.
+///
+/// ```yaml
+/// Backend:
+///   description: Backend developers
+///   members: [octocat, hubot]
+///   repositories: [api, worker]
+///   permission: push
+/// ```
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
+pub struct TeamsFile {
+    teams: BTreeMap<String, TeamDefinition>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct TeamDefinition {
+    /// Description of the team, used only when the team is created
+    #[serde(default)]
+    pub(crate) description: Option<String>,
+    /// GitHub logins of the team members
+    #[serde(default)]
+    pub(crate) members: Vec<String>,
+    /// Names of the repositories, with or without the organization part
+    #[serde(default)]
+    pub(crate) repositories: Vec<String>,
+    /// Permission of the team to the repositories, `push` (write) when not set
+    #[serde(default = "default_permission")]
+    pub(crate) permission: TeamRepositoryPermission,
+}
+
+fn default_permission() -> TeamRepositoryPermission {
+    TeamRepositoryPermission::Push
+}
+
+impl TeamsFile {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        migration_file::read(path)
+            .with_context(|| format!("Cannot parse teams file {}", path.display()))
+    }
+
+    pub(crate) fn teams(&self) -> impl Iterator<Item = (&String, &TeamDefinition)> {
+        self.teams.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn parses_teams_with_default_permission() {
+        let teams_file: TeamsFile = serde_yaml::from_str(
+            "Backend:\n  description: Backend developers\n  members: [octocat, hubot]\n  repositories: [api]\n\
+             Mobile:\n  permission: admin\n",
+        )
+        .unwrap();
+
+        let teams = teams_file.teams().collect::<Vec<_>>();
+        assert_eq!(teams.len(), 2);
+        let (name, backend) = teams[0];
+        assert_eq!(name, "Backend");
+        assert_eq!(backend.description.as_deref(), Some("Backend developers"));
+        assert_eq!(backend.members, vec!["octocat", "hubot"]);
+        assert_eq!(backend.repositories, vec!["api"]);
+        assert_eq!(backend.permission, TeamRepositoryPermission::Push);
+        let (name, mobile) = teams[1];
+        assert_eq!(name, "Mobile");
+        assert!(mobile.members.is_empty());
+        assert_eq!(mobile.permission, TeamRepositoryPermission::Admin);
+    }
+
+    #[test]
+    fn reads_json_teams_file() {
+        let dir = TempDir::new("migrate-bb-to-gh").unwrap();
+        let path = dir.path().join("teams.json");
+        std::fs::write(&path, r#"{"Backend": {"members": ["octocat"]}}"#).unwrap();
+
+        let teams_file = TeamsFile::from_file(&path).unwrap();
+
+        let teams = teams_file.teams().collect::<Vec<_>>();
+        assert_eq!(teams.len(), 1);
+        assert_eq!(teams[0].1.members, vec!["octocat"]);
+        assert_eq!(teams[0].1.permission, TeamRepositoryPermission::Push);
+    }
+}
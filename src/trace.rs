@@ -0,0 +1,21 @@
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Prints timings of the API requests, git commands and migration actions to standard error.
+///
+/// Enabled with `trace` (showing all spans of the tool) or `RUST_LOG` environment variable
+/// (e.g. `RUST_LOG=migrate_bb_to_gh=debug`), does nothing otherwise.
+pub fn configure_tracing(trace: bool) -> anyhow::Result<()> {
+    let filter = match std::env::var("RUST_LOG") {
+        Ok(directives) => EnvFilter::try_new(directives)?,
+        Err(_) if trace => EnvFilter::new("migrate_bb_to_gh=debug"),
+        Err(_) => return Ok(()),
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(std::io::stderr)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("Cannot configure tracing: {}", e))
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+/// Translates Bitbucket usernames to GitHub logins.
+///
+/// The mapping is read from a JSON file (object with Bitbucket usernames as keys)
+/// or from a CSV file with `bitbucket_user,github_login` rows.
+#[derive(Debug, Clone, Default)]
+pub struct UserMap {
+    users: HashMap<String, String>,
+}
+
+impl UserMap {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Cannot read user map file {}", path.display()))?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let users = if is_json {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("Cannot parse user map file {}", path.display()))?
+        } else {
+            Self::parse_csv(&contents)
+                .with_context(|| format!("Cannot parse user map file {}", path.display()))?
+        };
+
+        Ok(Self { users })
+    }
+
+    fn parse_csv(contents: &str) -> anyhow::Result<HashMap<String, String>> {
+        let mut users = HashMap::new();
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == "bitbucket_user,github_login" {
+                continue;
+            }
+
+            let (bitbucket_user, github_login) = line.split_once(',').ok_or_else(|| {
+                anyhow!(
+                    "Line {} is not in `bitbucket_user,github_login` format",
+                    idx + 1
+                )
+            })?;
+            users.insert(
+                bitbucket_user.trim().to_string(),
+                github_login.trim().to_string(),
+            );
+        }
+
+        Ok(users)
+    }
+
+    pub fn github_login(&self, bitbucket_user: &str) -> Option<&str> {
+        self.users.get(bitbucket_user).map(|login| login.as_str())
+    }
+
+    /// Splits the Bitbucket users into GitHub logins of the mapped users
+    /// and usernames of the users missing in the map.
+    pub fn translate<'a, I>(&self, bitbucket_users: I) -> (Vec<String>, Vec<String>)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut mapped = vec![];
+        let mut unmapped = vec![];
+        for user in bitbucket_users {
+            match self.github_login(user) {
+                Some(login) => mapped.push(login.to_string()),
+                None => unmapped.push(user.to_string()),
+            }
+        }
+
+        (mapped, unmapped)
+    }
+}